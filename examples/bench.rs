@@ -0,0 +1,20 @@
+//! Times the inner interpreter on a few tight loops.
+//!
+//! Run with `cargo run --release --example bench`.
+
+use std::time::Instant;
+
+use ruforth::{Forth, Interpreter};
+
+fn main() {
+    let mut forth = Forth::empty();
+    let intr = Interpreter::new();
+    intr.eval(&mut forth, ": sq dup * ;").unwrap();
+    intr.eval(&mut forth, ": sum-sq 0 swap 0 do i sq + loop ;").unwrap();
+    intr.eval(&mut forth, ": count-down begin 1 - dup 0 = until drop ;").unwrap();
+    for (name, code) in [("do loop + call", "1000000 sum-sq drop"), ("begin until", "1000000 count-down")] {
+        let start = Instant::now();
+        intr.eval(&mut forth, code).unwrap();
+        println!("{}: {:?}", name, start.elapsed());
+    }
+}
@@ -0,0 +1,226 @@
+use std::error::Error;
+use std::fmt;
+
+/// How many words are shown at each end of a long backtrace.
+const BACKTRACE_ENDS: usize = 4;
+
+/// The kind of failure, each mapping to an ANS Forth THROW code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    Abort,
+    AbortMessage(String),
+    StackUnderflow,
+    ReturnStackOverflow,
+    ReturnStackUnderflow,
+    DictionaryOverflow,
+    InvalidAddress,
+    DivisionByZero,
+    ResultOutOfRange,
+    UndefinedWord,
+    CompileOnly,
+    MissingName,
+    HoldOverflow,
+    LineTooLong,
+    ControlMismatch,
+    /// A token that is not a number in the base it was read in.
+    InvalidNumber(u32),
+    ReturnStackImbalance,
+    Interrupted,
+    NotCreated,
+    InvalidName,
+    IoError,
+    UnexpectedEnd,
+    /// IS would make a deferred word run itself.
+    DeferLoop,
+    Throw(i32),
+}
+
+impl ErrorKind {
+    /// The ANS Forth THROW code for this kind of error.
+    pub fn code(&self) -> i32 {
+        match self {
+            ErrorKind::Abort => -1,
+            ErrorKind::AbortMessage(_) => -2,
+            ErrorKind::StackUnderflow => -4,
+            ErrorKind::ReturnStackOverflow => -5,
+            ErrorKind::ReturnStackUnderflow => -6,
+            ErrorKind::DictionaryOverflow => -8,
+            ErrorKind::InvalidAddress => -9,
+            ErrorKind::DivisionByZero => -10,
+            ErrorKind::ResultOutOfRange => -11,
+            ErrorKind::UndefinedWord => -13,
+            ErrorKind::CompileOnly => -14,
+            ErrorKind::MissingName => -16,
+            ErrorKind::HoldOverflow => -17,
+            ErrorKind::LineTooLong => -18,
+            ErrorKind::ControlMismatch => -22,
+            ErrorKind::InvalidNumber(_) => -24,
+            ErrorKind::ReturnStackImbalance => -25,
+            ErrorKind::Interrupted => -28,
+            ErrorKind::NotCreated => -31,
+            ErrorKind::InvalidName => -32,
+            ErrorKind::IoError => -37,
+            ErrorKind::UnexpectedEnd => -39,
+            // First of the codes left to each system
+            ErrorKind::DeferLoop => -256,
+            ErrorKind::Throw(code) => *code,
+        }
+    }
+
+    /// The error kind a THROW of `code` stands for.
+    pub fn from_code(code: i32) -> ErrorKind {
+        match code {
+            -1 => ErrorKind::Abort,
+            -2 => ErrorKind::AbortMessage(String::new()),
+            -4 => ErrorKind::StackUnderflow,
+            -5 => ErrorKind::ReturnStackOverflow,
+            -6 => ErrorKind::ReturnStackUnderflow,
+            -8 => ErrorKind::DictionaryOverflow,
+            -9 => ErrorKind::InvalidAddress,
+            -10 => ErrorKind::DivisionByZero,
+            -11 => ErrorKind::ResultOutOfRange,
+            -13 => ErrorKind::UndefinedWord,
+            -14 => ErrorKind::CompileOnly,
+            -16 => ErrorKind::MissingName,
+            -17 => ErrorKind::HoldOverflow,
+            -18 => ErrorKind::LineTooLong,
+            -22 => ErrorKind::ControlMismatch,
+            -24 => ErrorKind::InvalidNumber(10),
+            -25 => ErrorKind::ReturnStackImbalance,
+            -28 => ErrorKind::Interrupted,
+            -31 => ErrorKind::NotCreated,
+            -32 => ErrorKind::InvalidName,
+            -37 => ErrorKind::IoError,
+            -39 => ErrorKind::UnexpectedEnd,
+            -256 => ErrorKind::DeferLoop,
+            _ => ErrorKind::Throw(code),
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            ErrorKind::Abort => "aborted",
+            ErrorKind::AbortMessage(msg) => msg,
+            ErrorKind::StackUnderflow => "stack underflow",
+            ErrorKind::ReturnStackOverflow => "return stack overflow",
+            ErrorKind::ReturnStackUnderflow => "return stack underflow",
+            ErrorKind::DictionaryOverflow => "dictionary overflow",
+            ErrorKind::InvalidAddress => "invalid memory address",
+            ErrorKind::DivisionByZero => "division by zero",
+            ErrorKind::ResultOutOfRange => "result out of range",
+            ErrorKind::UndefinedWord => "undefined word",
+            ErrorKind::CompileOnly => "interpreting a compile-only word",
+            ErrorKind::MissingName => "missing name",
+            ErrorKind::HoldOverflow => "pictured numeric output overflow",
+            ErrorKind::LineTooLong => "input line too long",
+            ErrorKind::ControlMismatch => "control structure mismatch",
+            ErrorKind::InvalidNumber(base) => return write!(f, "invalid number in base {}", base),
+            ErrorKind::ReturnStackImbalance => "return stack imbalance",
+            ErrorKind::Interrupted => "user interrupt",
+            ErrorKind::NotCreated => "word not defined by create",
+            ErrorKind::InvalidName => "invalid name",
+            ErrorKind::IoError => "output error",
+            ErrorKind::UnexpectedEnd => "unexpected end of input",
+            ErrorKind::DeferLoop => "deferred word would run itself",
+            ErrorKind::Throw(_) => "uncaught exception",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+/// Where a token starts in the input, counting lines read by the
+/// interpreter and columns from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// An error raised while evaluating Forth code, with the word that raised
+/// it and the position of the input token being evaluated.
+/// `backtrace` lists the words that were running, outermost first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForthError {
+    pub kind: ErrorKind,
+    pub word: Option<String>,
+    pub position: Option<Position>,
+    pub backtrace: Vec<String>,
+}
+
+impl ForthError {
+    pub fn new(kind: ErrorKind) -> ForthError {
+        ForthError {
+            kind,
+            word: None,
+            position: None,
+            backtrace: vec![],
+        }
+    }
+
+    pub fn code(&self) -> i32 {
+        self.kind.code()
+    }
+
+    /// Attaches the offending word, unless a more precise one is already set.
+    pub fn in_word(mut self, word: &str) -> ForthError {
+        if self.word.is_none() {
+            self.word = Some(word.to_string());
+        }
+        self
+    }
+
+    /// Records that the error went through `word` on its way out.
+    pub fn called_from(mut self, word: &str) -> ForthError {
+        self.backtrace.insert(0, word.to_string());
+        self
+    }
+
+    /// Attaches the input position, unless one is already set.
+    pub fn at(mut self, position: Position) -> ForthError {
+        if self.position.is_none() {
+            self.position = Some(position);
+        }
+        self
+    }
+}
+
+impl From<ErrorKind> for ForthError {
+    fn from(kind: ErrorKind) -> ForthError {
+        ForthError::new(kind)
+    }
+}
+
+impl fmt::Display for ForthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.kind, self.code())?;
+        if let Some(word) = &self.word {
+            write!(f, " in {}", word)?;
+        }
+        if let Some(position) = self.position {
+            write!(f, " at {}", position)?;
+        }
+        // Runaway recursion leaves thousands of frames, keep both ends
+        let trace = &self.backtrace;
+        if trace.len() > 2 * BACKTRACE_ENDS {
+            write!(
+                f,
+                " ({} -> ... -> {})",
+                trace[..BACKTRACE_ENDS].join(" -> "),
+                trace[trace.len() - BACKTRACE_ENDS..].join(" -> ")
+            )?;
+        } else if trace.len() > 1 {
+            write!(f, " ({})", trace.join(" -> "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ForthError {}
@@ -1,29 +1,276 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::rc::Rc;
 use std::result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-pub type ForthResult<T> = result::Result<T, String>;
-pub type ForthWord = (String, Vec<String>);
+use crate::error::{ErrorKind, ForthError};
+
+pub type ForthResult<T> = result::Result<T, ForthError>;
+
+/// Size in bytes of a cell in the data space.
+pub const CELL_SIZE: usize = 4;
+/// Size in bytes of the data space available to HERE and ALLOT.
+pub const DATA_SPACE_SIZE: usize = 64 * 1024;
+/// Address of the STATE cell, true while compiling a definition.
+pub const STATE: usize = 0;
+/// Address of the >IN cell, the offset in the input buffer of the next
+/// character to parse.
+pub const TO_IN: usize = STATE + CELL_SIZE;
+/// Address of the BASE cell, the radix numbers are read and shown in.
+pub const BASE: usize = TO_IN + CELL_SIZE;
+/// Address of the input buffer holding the line being interpreted.
+pub const TIB: usize = BASE + CELL_SIZE;
+/// Longest line of input the interpreter accepts.
+pub const TIB_SIZE: usize = 1024;
+/// Address of the buffer pictured numeric output is built in, from its end.
+pub const HOLD: usize = TIB + TIB_SIZE;
+/// Room for a double cell in binary, with a sign and some separators.
+pub const HOLD_SIZE: usize = 128;
+/// Data space taken by system variables, below the first HERE.
+const SYSTEM_SIZE: usize = HOLD + HOLD_SIZE;
+/// How many colon definitions may be running inside each other by default.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 4096;
+pub(crate) type ForthWord = (String, WordKind);
 pub type Operators = dyn Fn(&mut Forth) -> ForthResult<()>;
 
+// Tabs, carriage returns and other control characters separate names too
+fn is_space(c: u8) -> bool {
+    c <= b' '
+}
+
+/// What a dictionary entry does when it is executed.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum WordKind {
+    Colon(Rc<[Instruction]>),
+    Constant(i32),
+    /// Pushes the address of its data field, then runs the code after
+    /// DOES> in the defining word, given as its execution token and index.
+    Created { body: usize, does: Option<(usize, usize)> },
+    /// Pushes a number that TO can change.
+    Value(i32),
+    /// Runs the execution token IS last gave it, if any.
+    Deferred(Option<i32>),
+}
+
+/// One step of a compiled word definition. Words, literals and branch
+/// targets are all resolved when the word is defined.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Instruction {
+    /// Push a number.
+    Literal(i32),
+    /// Run the builtin with this index in the interpreter.
+    Builtin(usize),
+    /// Run the dictionary entry with this execution token.
+    Call(usize),
+    /// A word handled by the interpreter itself, such as the old `.`.
+    Word(String),
+    Print(String),
+    Branch(usize),
+    BranchIfZero(usize),
+    Do,
+    QuestionDo(usize),
+    Loop(usize),
+    PlusLoop(usize),
+    Leave(usize),
+    Exit,
+    AbortMessage(String),
+    Does,
+    /// Run the word this code was compiled into again.
+    Recurse,
+    /// Store the top of the stack in the VALUE with this execution token.
+    To(usize),
+    /// Make the DEFER with this execution token run the one on the stack.
+    Is(usize),
+    /// Push what the DEFER with this execution token runs.
+    ActionOf(usize),
+    /// Compile a word the interpreter compiles itself, such as IF, into the
+    /// open definition, as POSTPONE asks for.
+    Compile(String),
+}
+
+/// An open control structure while compiling a definition, holding the
+/// index of the branch that still needs its target.
+#[derive(Debug, Clone)]
+pub(crate) enum Control {
+    If(usize),
+    Else(usize),
+    Do { start: usize, leaves: Vec<usize> },
+    Begin(usize),
+    While { dest: usize, orig: usize },
+}
+
+impl Control {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::If(_) => "if",
+            Control::Else(_) => "else",
+            Control::Do { .. } => "do",
+            Control::Begin(_) => "begin",
+            Control::While { .. } => "while",
+        }
+    }
+}
+
+/// A colon definition being compiled, which may span several lines of input.
+#[derive(Debug, Clone)]
+pub(crate) struct Definition {
+    pub name: String,
+    pub code: Vec<Instruction>,
+    pub control: Vec<Control>,
+}
+
+impl Definition {
+    pub fn new(name: &str) -> Definition {
+        Definition {
+            name: name.to_string(),
+            code: vec![],
+            control: vec![],
+        }
+    }
+}
+
+/// A colon definition being run by the inner interpreter.
+#[derive(Debug, Clone)]
+pub(crate) struct Frame {
+    /// The word being executed, as named in backtraces.
+    pub xt: usize,
+    /// The word the code was compiled into, which differs from `xt` after DOES>.
+    pub owner: usize,
+    pub body: Rc<[Instruction]>,
+    pub pc: usize,
+    /// Stack depth to restore if the frame was entered by CATCH.
+    pub catch_depth: Option<usize>,
+    return_depth: usize,
+    caller_floor: usize,
+}
+
+impl Frame {
+    pub fn new(xt: usize, owner: usize, body: Rc<[Instruction]>, pc: usize) -> Frame {
+        Frame {
+            xt,
+            owner,
+            body,
+            pc,
+            catch_depth: None,
+            return_depth: 0,
+            caller_floor: 0,
+        }
+    }
+
+    /// Return stack depth when the frame was entered.
+    pub fn return_depth(&self) -> usize {
+        self.return_depth
+    }
+}
+
+/// An in-memory output that can still be read once a clone of it has been
+/// handed to a Forth.
+#[derive(Debug, Clone, Default)]
+pub struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn new() -> SharedBuffer {
+        SharedBuffer::default()
+    }
+
+    /// Everything written so far.
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+
+    /// Everything written so far, as raw bytes.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.0.borrow().clone()
+    }
+
+    /// Everything written so far, leaving the buffer empty.
+    pub fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.0.borrow_mut());
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 pub struct Forth {
     stack: Vec<i32>,
-    words: HashMap<String, ForthWord>,
-    constants: HashMap<String, i32>
+    return_stack: Vec<i32>,
+    return_floor: usize,
+    frames: Vec<Frame>,
+    max_call_depth: usize,
+    words: Vec<ForthWord>,
+    word_index: HashMap<String, usize>,
+    immediate: HashSet<usize>,
+    latest: Option<usize>,
+    definition: Option<Definition>,
+    source_len: usize,
+    line: usize,
+    hold: usize,
+    memory: Vec<u8>,
+    here: usize,
+    interrupt: Arc<AtomicBool>,
+    checked_arithmetic: bool,
+    output: Box<dyn Write>,
+    errors: Box<dyn Write>,
 }
 
 impl Forth {
+    /// A Forth writing to stdout, and its errors to stderr.
     pub fn empty() -> Forth {
-        Forth {
+        Forth::with_output(Box::new(io::stdout()), Box::new(io::stderr()))
+    }
+
+    /// A Forth writing into memory, returned along with its output and its
+    /// errors.
+    pub fn buffered() -> (Forth, SharedBuffer, SharedBuffer) {
+        let output = SharedBuffer::new();
+        let errors = SharedBuffer::new();
+        let forth = Forth::with_output(Box::new(output.clone()), Box::new(errors.clone()));
+        (forth, output, errors)
+    }
+
+    pub fn with_output(output: Box<dyn Write>, errors: Box<dyn Write>) -> Forth {
+        let mut forth = Forth {
             stack: vec![],
-            words: HashMap::new(),
-            constants: HashMap::new(),
-        }
+            return_stack: vec![],
+            return_floor: 0,
+            frames: vec![],
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            words: vec![],
+            word_index: HashMap::new(),
+            immediate: HashSet::new(),
+            latest: None,
+            definition: None,
+            source_len: 0,
+            line: 0,
+            hold: HOLD + HOLD_SIZE,
+            memory: vec![0; DATA_SPACE_SIZE],
+            here: SYSTEM_SIZE,
+            interrupt: Arc::new(AtomicBool::new(false)),
+            checked_arithmetic: false,
+            output,
+            errors,
+        };
+        forth.set_base(10);
+        forth
     }
 
-    pub fn pop(&mut self, msg: String) -> ForthResult<i32> {
+    pub fn pop(&mut self) -> ForthResult<i32> {
         match self.stack.pop() {
             Some(n) => Ok(n),
-            None => Err(msg),
+            None => Err(ErrorKind::StackUnderflow.into()),
         }
     }
 
@@ -31,39 +278,465 @@ impl Forth {
         self.stack.push(val);
     }
 
+    /// Pops the return stack, refusing to reach below the cells that belong
+    /// to the callers of the running word.
+    pub fn pop_return(&mut self) -> ForthResult<i32> {
+        if self.return_stack.len() <= self.return_floor {
+            return Err(ErrorKind::ReturnStackUnderflow.into());
+        }
+        match self.return_stack.pop() {
+            Some(n) => Ok(n),
+            None => Err(ErrorKind::ReturnStackUnderflow.into()),
+        }
+    }
+
+    /// Fails unless `n` cells can be popped from the return stack, so that
+    /// words taking several cells leave it alone when they cannot.
+    pub fn check_return(&self, n: usize) -> ForthResult<()> {
+        if self.return_stack.len() < self.return_floor + n {
+            return Err(ErrorKind::ReturnStackUnderflow.into());
+        }
+        Ok(())
+    }
+
+    pub fn push_return(&mut self, val: i32) {
+        self.return_stack.push(val);
+    }
+
+    /// Reads the return stack `depth` cells below the top without popping.
+    pub fn peek_return(&self, depth: usize) -> ForthResult<i32> {
+        match self.return_stack.len().checked_sub(depth + 1) {
+            Some(i) => Ok(self.return_stack[i]),
+            None => Err(ErrorKind::ReturnStackUnderflow.into()),
+        }
+    }
+
+    pub fn return_depth(&self) -> usize {
+        self.return_stack.len()
+    }
+
+    pub(crate) fn truncate_return(&mut self, depth: usize) {
+        self.return_stack.truncate(depth);
+    }
+
+    /// Enters a colon definition. Its callers' return stack cells are out of
+    /// its reach until the frame is popped again.
+    pub(crate) fn push_frame(&mut self, mut frame: Frame) -> ForthResult<()> {
+        if self.frames.len() >= self.max_call_depth {
+            return Err(ErrorKind::ReturnStackOverflow.into());
+        }
+        frame.return_depth = self.return_stack.len();
+        frame.caller_floor = std::mem::replace(&mut self.return_floor, frame.return_depth);
+        self.frames.push(frame);
+        Ok(())
+    }
+
+    pub(crate) fn pop_frame(&mut self) -> Option<Frame> {
+        let frame = self.frames.pop()?;
+        self.return_floor = frame.caller_floor;
+        Some(frame)
+    }
+
+    pub(crate) fn current_frame(&mut self) -> Option<&mut Frame> {
+        self.frames.last_mut()
+    }
+
+    pub(crate) fn frame_depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Sets the stack back to `depth` cells, as CATCH does after a THROW.
+    /// Missing cells are filled with zeros.
+    pub(crate) fn set_depth(&mut self, depth: usize) {
+        self.stack.resize(depth, 0);
+    }
+
     pub fn clear(&mut self) {
         self.stack.clear();
     }
 
-    pub fn print_stack(&self) {
-        println!("{:?}", self.stack);
+    pub fn print_stack(&mut self) -> ForthResult<()> {
+        let numbers: Vec<String> = self.stack.iter().map(|&n| self.format_number(n)).collect();
+        self.write_output(&format!("[{}]\n", numbers.join(", ")))
+    }
+
+    /// Writes to the output right away, even without a newline.
+    pub fn write_output(&mut self, text: &str) -> ForthResult<()> {
+        self.write_bytes(text.as_bytes())
+    }
+
+    /// Writes bytes to the output as they are, whether they are UTF-8 or not.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> ForthResult<()> {
+        self.output
+            .write_all(bytes)
+            .and_then(|_| self.output.flush())
+            .map_err(|_| ErrorKind::IoError.into())
+    }
+
+    /// Writes to the error channel, which is kept apart from the output.
+    pub fn write_error(&mut self, text: &str) -> ForthResult<()> {
+        self.errors
+            .write_all(text.as_bytes())
+            .and_then(|_| self.errors.flush())
+            .map_err(|_| ErrorKind::IoError.into())
     }
 
     pub fn get_stack(&self) -> Vec<i32> {
         self.stack.clone()
     }
 
-    pub fn add_word(&mut self, name: &str, word: ForthWord) -> Option<ForthWord> {
-        self.words.insert(name.to_string(), word)
+    /// Defines a word and returns its execution token. A previous word with
+    /// the same name is hidden from lookups but stays in the dictionary, so
+    /// definitions compiled against it keep using it.
+    pub(crate) fn add_word(&mut self, name: &str, word: ForthWord) -> usize {
+        let xt = self.words.len();
+        self.words.push(word);
+        self.word_index.insert(name.to_string(), xt);
+        self.latest = Some(xt);
+        xt
     }
 
-    pub fn get_word(&self, name: &str) -> Option<ForthWord> {
-        match self.words.get(name) {
-            Some(word) => Some(word.clone()),
-            None => None,
+    /// Makes the most recent word, which must come from CREATE, run the
+    /// code of word `xt` from index `start` whenever it is executed.
+    pub(crate) fn set_does(&mut self, xt: usize, start: usize) -> ForthResult<()> {
+        match self.latest.map(|latest| &mut self.words[latest].1) {
+            Some(WordKind::Created { does, .. }) => {
+                *does = Some((xt, start));
+                Ok(())
+            }
+            _ => Err(ErrorKind::NotCreated.into()),
         }
     }
 
-    pub fn add_constant(&mut self, name: &str, value: i32) -> Option<i32> {
-        self.constants.insert(name.to_string(), value)
+    /// Makes the most recent word run even while compiling.
+    pub fn set_immediate(&mut self) {
+        if let Some(latest) = self.latest {
+            self.immediate.insert(latest);
+        }
+    }
+
+    pub fn is_immediate(&self, xt: usize) -> bool {
+        self.immediate.contains(&xt)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn get_word(&self, name: &str) -> Option<ForthWord> {
+        self.find_word(name).and_then(|xt| self.words.get(xt).cloned())
+    }
+
+    /// Returns the execution token of a word, to be put on the stack.
+    pub fn find_word(&self, name: &str) -> Option<usize> {
+        self.word_index.get(name).cloned()
+    }
+
+    pub(crate) fn word_kind(&self, xt: usize) -> Option<&WordKind> {
+        self.words.get(xt).map(|word| &word.1)
     }
 
-    pub fn get_constant(&self, name: &str) -> Option<i32> {
-        self.constants.get(name).cloned()
+    pub(crate) fn word_kind_mut(&mut self, xt: usize) -> Option<&mut WordKind> {
+        self.words.get_mut(xt).map(|word| &mut word.1)
+    }
+
+    pub fn word_name(&self, xt: usize) -> &str {
+        self.words.get(xt).map_or("", |word| &word.0)
+    }
+
+    /// Opens a definition and switches to compiling.
+    pub(crate) fn start_definition(&mut self, definition: Definition) {
+        self.definition = Some(definition);
+        self.set_state(true);
+    }
+
+    /// Whether a colon definition is open, even while `[` interprets.
+    pub fn in_definition(&self) -> bool {
+        self.definition.is_some()
+    }
+
+    /// The open definition, for words that compile into it.
+    pub(crate) fn definition_mut(&mut self) -> Option<&mut Definition> {
+        self.definition.as_mut()
+    }
+
+    /// Takes the open definition out, leaving STATE as it is.
+    pub(crate) fn take_definition(&mut self) -> Option<Definition> {
+        self.definition.take()
+    }
+
+    /// Puts back a definition taken out, leaving STATE as it is.
+    pub(crate) fn restore_definition(&mut self, definition: Definition) {
+        self.definition = Some(definition);
+    }
+
+    /// Whether the interpreter is compiling, as held by the STATE cell.
+    pub fn state(&self) -> bool {
+        self.system_cell(STATE) != 0
+    }
+
+    pub(crate) fn set_state(&mut self, compiling: bool) {
+        let flag = if compiling { -1 } else { 0 };
+        self.set_system_cell(STATE, flag);
+    }
+
+    /// Makes `text` the input source as the next line of input.
+    pub(crate) fn load_line(&mut self, text: &str) -> ForthResult<()> {
+        let text = text.as_bytes();
+        self.line += 1;
+        if text.len() > TIB_SIZE {
+            return Err(ErrorKind::LineTooLong.into());
+        }
+        self.memory[TIB..TIB + text.len()].copy_from_slice(text);
+        self.source_len = text.len();
+        self.set_to_in(0);
+        Ok(())
+    }
+
+    /// Address and length of the line being interpreted.
+    pub fn source(&self) -> (usize, usize) {
+        (TIB, self.source_len)
+    }
+
+    /// Number of lines of input read so far, the current one included.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The parse position held by >IN, kept within the line.
+    pub fn to_in(&self) -> usize {
+        let to_in = self.system_cell(TO_IN);
+        usize::try_from(to_in).unwrap_or(0).min(self.source_len)
+    }
+
+    pub fn set_to_in(&mut self, to_in: usize) {
+        self.set_system_cell(TO_IN, to_in as i32);
+    }
+
+    /// Parses up to `delimiter` or the end of the line, whichever comes
+    /// first, and returns the address and length of what is in between.
+    pub fn parse(&mut self, delimiter: u8) -> (usize, usize) {
+        self.scan(|c| c == delimiter)
+    }
+
+    /// Skips leading whitespace and parses the next name, which is empty
+    /// at the end of the line.
+    pub fn parse_name(&mut self) -> (usize, usize) {
+        let start = self.to_in();
+        let line = &self.memory[TIB + start..TIB + self.source_len];
+        let skipped = line.iter().take_while(|&&c| is_space(c)).count();
+        self.set_to_in(start + skipped);
+        self.scan(is_space)
+    }
+
+    /// Parses the next name as a string, along with its column in the line,
+    /// starting at 1.
+    pub(crate) fn next_word(&mut self) -> Option<(String, usize)> {
+        match self.parse_name() {
+            (_, 0) => None,
+            (addr, len) => Some((self.text(addr, len), addr - TIB + 1)),
+        }
+    }
+
+    /// Skips the rest of the line.
+    pub(crate) fn skip_line(&mut self) {
+        self.set_to_in(self.source_len);
+    }
+
+    // Parses up to the first delimiter, which is consumed as well
+    fn scan(&mut self, is_delimiter: impl Fn(u8) -> bool) -> (usize, usize) {
+        let start = self.to_in();
+        let line = &self.memory[TIB + start..TIB + self.source_len];
+        let len = line.iter().position(|&c| is_delimiter(c)).unwrap_or(line.len());
+        self.set_to_in((start + len + 1).min(self.source_len));
+        (TIB + start, len)
+    }
+
+    /// The characters at `addr`, which must lie in the data space.
+    pub(crate) fn text(&self, addr: usize, len: usize) -> String {
+        String::from_utf8_lossy(&self.memory[addr..addr + len]).into_owned()
+    }
+
+    /// The `len` bytes at `addr`, checked like any memory access.
+    pub fn bytes_at(&self, addr: i32, len: i32) -> ForthResult<&[u8]> {
+        let len = usize::try_from(len).map_err(|_| ForthError::from(ErrorKind::InvalidAddress))?;
+        let range = self.data_range(addr, len)?;
+        Ok(&self.memory[range])
+    }
+
+    /// The `len` characters at `addr`, checked like any memory access.
+    pub fn string_at(&self, addr: i32, len: i32) -> ForthResult<String> {
+        let bytes = self.bytes_at(addr, len)?;
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// Empties the pictured numeric output buffer, as `<#` does.
+    pub(crate) fn begin_hold(&mut self) {
+        self.hold = HOLD + HOLD_SIZE;
+    }
+
+    /// Adds a character in front of the pictured numeric output.
+    pub fn hold(&mut self, c: u8) -> ForthResult<()> {
+        if self.hold == HOLD {
+            return Err(ErrorKind::HoldOverflow.into());
+        }
+        self.hold -= 1;
+        self.memory[self.hold] = c;
+        Ok(())
+    }
+
+    /// Address and length of the pictured numeric output so far.
+    pub(crate) fn held(&self) -> (usize, usize) {
+        (self.hold, HOLD + HOLD_SIZE - self.hold)
+    }
+
+    pub fn base(&self) -> u32 {
+        self.system_cell(BASE) as u32
+    }
+
+    pub fn set_base(&mut self, base: u32) {
+        self.set_system_cell(BASE, base as i32);
+    }
+
+    /// BASE if numbers can be written in it, or 10.
+    pub fn radix(&self) -> u32 {
+        match self.base() {
+            base @ 2..=36 => base,
+            _ => 10,
+        }
+    }
+
+    /// Writes `n` in BASE, falling back to decimal if BASE makes no sense.
+    pub fn format_number(&self, n: i32) -> String {
+        let digits = self.format_unsigned(n.unsigned_abs());
+        if n < 0 {
+            format!("-{}", digits)
+        } else {
+            digits
+        }
+    }
+
+    pub fn format_unsigned(&self, mut value: u32) -> String {
+        let base = self.radix();
+        let mut digits = vec![];
+        loop {
+            digits.push(std::char::from_digit(value % base, base).unwrap_or('?').to_ascii_uppercase());
+            value /= base;
+            if value == 0 {
+                break;
+            }
+        }
+        digits.iter().rev().collect()
+    }
+
+    /// Address of the next free byte of the data space.
+    pub fn here(&self) -> usize {
+        self.here
+    }
+
+    /// Reserves `n` bytes of data space, or gives them back if negative.
+    /// The system area at the bottom is never given back.
+    pub fn allot(&mut self, n: i32) -> ForthResult<()> {
+        let here = self.here as i64 + n as i64;
+        // The system variables stay below HERE, where @ and ! can reach them
+        if here < SYSTEM_SIZE as i64 {
+            return Err(ErrorKind::InvalidAddress.into());
+        }
+        if here > DATA_SPACE_SIZE as i64 {
+            return Err(ErrorKind::DictionaryOverflow.into());
+        }
+        self.here = here as usize;
+        Ok(())
+    }
+
+    pub fn align(&mut self) {
+        self.here = Self::aligned(self.here);
+    }
+
+    pub fn aligned(addr: usize) -> usize {
+        addr.div_ceil(CELL_SIZE) * CELL_SIZE
+    }
+
+    pub fn fetch(&self, addr: i32) -> ForthResult<i32> {
+        let range = self.data_range(addr, CELL_SIZE)?;
+        let mut cell = [0; CELL_SIZE];
+        cell.copy_from_slice(&self.memory[range]);
+        Ok(i32::from_le_bytes(cell))
+    }
+
+    pub fn store(&mut self, addr: i32, val: i32) -> ForthResult<()> {
+        let range = self.data_range(addr, CELL_SIZE)?;
+        self.memory[range].copy_from_slice(&val.to_le_bytes());
+        Ok(())
+    }
+
+    pub fn fetch_char(&self, addr: i32) -> ForthResult<u8> {
+        let range = self.data_range(addr, 1)?;
+        Ok(self.memory[range.start])
+    }
+
+    pub fn store_char(&mut self, addr: i32, val: u8) -> ForthResult<()> {
+        let range = self.data_range(addr, 1)?;
+        self.memory[range.start] = val;
+        Ok(())
+    }
+
+    // System cells are always there, wherever HERE is
+    fn system_cell(&self, addr: usize) -> i32 {
+        let mut cell = [0; CELL_SIZE];
+        cell.copy_from_slice(&self.memory[addr..addr + CELL_SIZE]);
+        i32::from_le_bytes(cell)
+    }
+
+    fn set_system_cell(&mut self, addr: usize, val: i32) {
+        self.memory[addr..addr + CELL_SIZE].copy_from_slice(&val.to_le_bytes());
+    }
+
+    // Only the part of the data space below HERE may be read or written
+    fn data_range(&self, addr: i32, len: usize) -> ForthResult<std::ops::Range<usize>> {
+        match usize::try_from(addr) {
+            Ok(start) if start + len <= self.here => Ok(start..start + len),
+            _ => Err(ErrorKind::InvalidAddress.into()),
+        }
+    }
+
+    /// When set, arithmetic overflow raises an error instead of wrapping.
+    pub fn set_checked_arithmetic(&mut self, checked: bool) {
+        self.checked_arithmetic = checked;
+    }
+
+    pub fn checked_arithmetic(&self) -> bool {
+        self.checked_arithmetic
+    }
+
+    /// Flag that a host (e.g. a Ctrl-C handler) can set to stop a running
+    /// loop. It is checked on every backward jump of a compiled word.
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        self.interrupt.clone()
+    }
+
+    pub(crate) fn clear_interrupt(&mut self) {
+        self.interrupt.store(false, Ordering::SeqCst);
+    }
+
+    pub fn check_interrupt(&self) -> ForthResult<()> {
+        if self.interrupt.load(Ordering::SeqCst) {
+            Err(ErrorKind::Interrupted.into())
+        } else {
+            Ok(())
+        }
     }
 
-    pub fn get_words(&self) -> HashMap<String, ForthWord> {
-        self.words.clone()
+    /// Words visible by name, without the ones hidden by a redefinition.
+    pub(crate) fn get_words(&self) -> Vec<ForthWord> {
+        let mut visible: Vec<usize> = self.word_index.values().cloned().collect();
+        visible.sort_unstable();
+        visible.into_iter().map(|xt| self.words[xt].clone()).collect()
     }
 
 }
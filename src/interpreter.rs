@@ -1,249 +1,1257 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::slice::Iter;
+use std::rc::Rc;
 
-use crate::forth::{Forth, ForthResult, ForthWord, Operators};
+use crate::error::{ErrorKind, ForthError, Position};
+use crate::forth::{
+    Control, Definition, Forth, ForthResult, Frame, Instruction, Operators, WordKind, CELL_SIZE,
+};
 use crate::operators;
 use crate::operators_binary;
+use crate::operators_input;
+use crate::operators_memory;
+use crate::operators_output;
+use crate::operators_return;
 
-pub struct Interpreter<'a> {
-    pub commands: HashMap<String, &'a Operators>,
+/// Groups of builtin words an interpreter can be built with. Defining
+/// words, control structures, CATCH and EXECUTE are always there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordSet {
+    Arithmetic,
+    Stack,
+    Comparison,
+    Memory,
+    /// BASE and the words setting it.
+    Numbers,
+    ReturnStack,
+    Input,
+    Output,
+    Exceptions,
 }
 
-impl<'a> Interpreter<'a> {
-    pub fn eval(&self, forth: &mut Forth, instruction: &str) {
-        let tokens: Vec<_> = instruction.split(' ').map(|s| s.trim().to_string()).collect();
-        self.eval_tokens(forth, &mut tokens.iter());
-    }
+impl WordSet {
+    pub const ALL: [WordSet; 9] = [
+        WordSet::Arithmetic,
+        WordSet::Stack,
+        WordSet::Comparison,
+        WordSet::Memory,
+        WordSet::Numbers,
+        WordSet::ReturnStack,
+        WordSet::Input,
+        WordSet::Output,
+        WordSet::Exceptions,
+    ];
+}
 
+/// Chooses the word sets and options of an [`Interpreter`].
+#[derive(Debug, Clone)]
+pub struct InterpreterBuilder {
+    word_sets: Vec<WordSet>,
+    case_sensitive: bool,
+    legacy_dot: bool,
+}
+
+impl InterpreterBuilder {
+    /// Starts from every word set, case-insensitive lookup and standard `.`.
     pub fn new() -> Self {
+        InterpreterBuilder {
+            word_sets: WordSet::ALL.to_vec(),
+            case_sensitive: false,
+            legacy_dot: false,
+        }
+    }
+
+    /// Leaves out every word set, so that only the ones added back with
+    /// `with` are built.
+    pub fn bare(mut self) -> Self {
+        self.word_sets.clear();
+        self
+    }
+
+    pub fn with(mut self, set: WordSet) -> Self {
+        if !self.word_sets.contains(&set) {
+            self.word_sets.push(set);
+        }
+        self
+    }
+
+    pub fn without(mut self, set: WordSet) -> Self {
+        self.word_sets.retain(|&s| s != set);
+        self
+    }
+
+    /// See [`Interpreter::set_case_sensitive`].
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// See [`Interpreter::set_legacy_dot`].
+    pub fn legacy_dot(mut self, legacy_dot: bool) -> Self {
+        self.legacy_dot = legacy_dot;
+        self
+    }
+
+    pub fn build(&self) -> Interpreter {
         let mut intr = Interpreter {
-            commands: HashMap::new(),
+            commands: vec![],
+            command_index: HashMap::new(),
+            case_sensitive: self.case_sensitive,
+            legacy_dot: self.legacy_dot,
         };
-
-        intr.init();
+        intr.add_interpreter_words();
+        for &set in &self.word_sets {
+            intr.add_word_set(set);
+        }
         intr
     }
+}
 
-    fn init(&mut self) {
-        self.commands.insert("+".to_owned(), &operators::add);
-        self.commands.insert("-".to_owned(), &operators::sub);
-        self.commands.insert("*".to_owned(), &operators::mul);
-        self.commands.insert("/".to_owned(), &operators::div);
-        self.commands.insert("mod".to_owned(), &operators::modulus);
-        self.commands.insert("negate".to_owned(), &operators::negate);
-        self.commands.insert("abs".to_owned(), &operators::abs);
-        self.commands.insert("max".to_owned(), &operators::max);
-        self.commands.insert("min".to_owned(), &operators::min);
+impl Default for InterpreterBuilder {
+    fn default() -> Self {
+        InterpreterBuilder::new()
+    }
+}
 
-        self.commands.insert("dup".to_owned(), &operators::dup);
-        self.commands.insert("swap".to_owned(), &operators::swap);
-        self.commands.insert("rot".to_owned(), &operators::rot);
-        self.commands.insert("drop".to_owned(), &operators::drop);
-        self.commands.insert("nip".to_owned(), &operators::nip);
-        self.commands.insert("tuck".to_owned(), &operators::tuck);
-        self.commands.insert("over".to_owned(), &operators::over);
+/// A word implemented in Rust.
+pub struct Builtin {
+    name: String,
+    /// None for the words the interpreter runs itself, such as `constant`.
+    op: Option<Box<Operators>>,
+    effect: Option<String>,
+    inputs: usize,
+}
 
-        self.commands.insert("cr".to_owned(), &operators::cr);
-        self.commands.insert("clearstack".to_owned(), &operators::clearstack);
+impl Builtin {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-        self.commands.insert("=".to_owned(), &operators_binary::equals);
-        self.commands.insert("!=".to_owned(), &operators_binary::not_equals);
-        self.commands.insert(">".to_owned(), &operators_binary::greater_than);
-        self.commands.insert("<".to_owned(), &operators_binary::less_than);
-        self.commands.insert(">=".to_owned(), &operators_binary::greater_than_equals);
-        self.commands.insert("<=".to_owned(), &operators_binary::less_than_equals);
-        self.commands.insert("invert".to_owned(), &operators_binary::invert);
+    /// Documents the word with a stack effect such as `( key -- value )`.
+    /// The word is not run unless the stack holds as many cells as are
+    /// listed before `--`, or before `)` if it leaves nothing.
+    pub fn stack_effect(&mut self, effect: &str) -> &mut Builtin {
+        let inputs = effect
+            .split_whitespace()
+            .skip_while(|&s| s == "(")
+            .take_while(|&s| s != "--" && s != ")");
+        self.inputs = inputs.count();
+        self.effect = Some(effect.to_string());
+        self
+    }
 
+    pub fn effect(&self) -> Option<&str> {
+        self.effect.as_deref()
     }
+}
 
-    fn eval_commands(&self, op: &str, forth: &mut Forth) -> Option<ForthResult<()>> {
-        if self.commands.contains_key(op) {
-            let opr = self.commands.get(op).unwrap();
-            Some(opr(forth))
+/// What an execution token on the stack stands for. Builtins get negative
+/// tokens, so that they never clash with the dictionary of a Forth.
+#[derive(Clone, Copy)]
+enum Token {
+    Word(usize),
+    Builtin(usize),
+}
+
+/// Reads Forth source into a [`Forth`], with the builtin words it was built
+/// with.
+pub struct Interpreter {
+    commands: Vec<Builtin>,
+    command_index: HashMap<String, usize>,
+    case_sensitive: bool,
+    legacy_dot: bool,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    /// Interprets `text` line by line, continuing any definition left open
+    /// by the previous call. Each line is read into the input buffer, so a
+    /// line longer than [`TIB_SIZE`](crate::forth::TIB_SIZE) bytes fails
+    /// with `LineTooLong` before any of it runs.
+    pub fn eval(&self, forth: &mut Forth, text: &str) -> ForthResult<()> {
+        forth.clear_interrupt();
+        let mut result = Ok(());
+        for line in text.split('\n') {
+            result = forth.load_line(line).and_then(|_| self.eval_source(forth));
+            if result.is_err() {
+                break;
+            }
+        }
+        if let Err(ForthError { kind: ErrorKind::Abort | ErrorKind::AbortMessage(_), .. }) = result {
+            forth.clear();
+            forth.truncate_return(0);
+        }
+        result
+    }
+
+    /// Called when the input runs out: a definition still open is dropped
+    /// and reported.
+    pub fn finish(&self, forth: &mut Forth) -> ForthResult<()> {
+        forth.set_state(false);
+        match forth.take_definition() {
+            Some(definition) => Err(ForthError::from(ErrorKind::UnexpectedEnd).in_word(&definition.name)),
+            None => Ok(()),
+        }
+    }
+
+    /// An interpreter with every word set.
+    pub fn new() -> Self {
+        InterpreterBuilder::new().build()
+    }
+
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::new()
+    }
+
+    /// Makes `DUP` and `dup` different words, as they were in older
+    /// versions. Set it before defining any words.
+    pub fn set_case_sensitive(&mut self, case_sensitive: bool) {
+        self.case_sensitive = case_sensitive;
+    }
+
+    /// Makes `.` show the whole stack as `> [1, 2]` and leave it alone, as
+    /// it did in older versions.
+    pub fn set_legacy_dot(&mut self, legacy_dot: bool) {
+        self.legacy_dot = legacy_dot;
+    }
+
+    // The name a word is stored and looked up under
+    fn key<'s>(&self, name: &'s str) -> Cow<'s, str> {
+        if self.case_sensitive || !name.bytes().any(|c| c.is_ascii_uppercase()) {
+            Cow::Borrowed(name)
         } else {
-            None
+            Cow::Owned(name.to_ascii_lowercase())
         }
     }
 
-    fn eval_word(&self, name: &str, forth: &mut Forth) -> Option<ForthResult<()>> {
-        match forth.get_word(name) {
-            Some(ref word) => {
-                self.eval_tokens(forth, &mut word.1.iter());
-                Some(Ok(()))
+    fn add_word_set(&mut self, set: WordSet) {
+        match set {
+            WordSet::Arithmetic => {
+                self.add_command("+", operators::add);
+                self.add_command("-", operators::sub);
+                self.add_command("*", operators::mul);
+                self.add_command("/", operators::div);
+                self.add_command("mod", operators::modulus);
+                self.add_command("negate", operators::negate);
+                self.add_command("abs", operators::abs);
+                self.add_command("max", operators::max);
+                self.add_command("min", operators::min);
+            }
+            WordSet::Stack => {
+                self.add_command("dup", operators::dup);
+                self.add_command("swap", operators::swap);
+                self.add_command("rot", operators::rot);
+                self.add_command("drop", operators::drop);
+                self.add_command("nip", operators::nip);
+                self.add_command("tuck", operators::tuck);
+                self.add_command("over", operators::over);
+                self.add_command("depth", operators::depth);
+                self.add_command("clearstack", operators::clearstack);
+            }
+            WordSet::Comparison => {
+                self.add_command("=", operators_binary::equals);
+                self.add_command("!=", operators_binary::not_equals);
+                self.add_command(">", operators_binary::greater_than);
+                self.add_command("<", operators_binary::less_than);
+                self.add_command(">=", operators_binary::greater_than_equals);
+                self.add_command("<=", operators_binary::less_than_equals);
+                self.add_command("invert", operators_binary::invert);
+            }
+            WordSet::Memory => {
+                self.add_command("@", operators_memory::fetch);
+                self.add_command("!", operators_memory::store);
+                self.add_command("+!", operators_memory::plus_store);
+                self.add_command("c@", operators_memory::c_fetch);
+                self.add_command("c!", operators_memory::c_store);
+                self.add_command("here", operators_memory::here);
+                self.add_command(">body", operators_memory::to_body);
+                self.add_command("state", operators_memory::state);
+                self.add_command("allot", operators_memory::allot);
+                self.add_command(",", operators_memory::comma);
+                self.add_command("c,", operators_memory::c_comma);
+                self.add_command("cells", operators_memory::cells);
+                self.add_command("cell+", operators_memory::cell_plus);
+                self.add_command("align", operators_memory::align);
+            }
+            WordSet::Numbers => {
+                self.add_command("base", operators_memory::base);
+                self.add_command("hex", operators_memory::hex);
+                self.add_command("decimal", operators_memory::decimal);
+                self.add_command("octal", operators_memory::octal);
+                self.add_command("binary", operators_memory::binary);
+            }
+            WordSet::ReturnStack => {
+                self.add_command("i", operators_return::i);
+                self.add_command("j", operators_return::j);
+                self.add_command("unloop", operators_return::unloop);
+                self.add_command(">r", operators_return::to_r);
+                self.add_command("r>", operators_return::r_from);
+                self.add_command("r@", operators_return::r_fetch);
+                self.add_command("rdrop", operators_return::rdrop);
+                self.add_command("2>r", operators_return::two_to_r);
+                self.add_command("2r>", operators_return::two_r_from);
+                self.add_command("2r@", operators_return::two_r_fetch);
+            }
+            WordSet::Input => {
+                self.add_command("source", operators_input::source);
+                self.add_command(">in", operators_input::to_in);
+                self.add_command("parse", operators_input::parse);
+                self.add_command("parse-name", operators_input::parse_name);
+            }
+            WordSet::Output => {
+                self.add_command("cr", operators::cr);
+                self.add_command(".", operators_output::dot);
+                self.add_command("u.", operators_output::u_dot);
+                self.add_command(".r", operators_output::dot_r);
+                self.add_command("u.r", operators_output::u_dot_r);
+                self.add_command(".s", operators_output::dot_s);
+                self.add_command("?", operators_output::question);
+                self.add_command("type", operators_output::type_string);
+                self.add_command("<#", operators_output::less_number_sign);
+                self.add_command("#", operators_output::number_sign);
+                self.add_command("#s", operators_output::number_sign_s);
+                self.add_command("hold", operators_output::hold);
+                self.add_command("holds", operators_output::holds);
+                self.add_command("sign", operators_output::sign);
+                self.add_command("#>", operators_output::number_sign_greater);
+            }
+            WordSet::Exceptions => {
+                self.add_command("throw", operators::throw);
+                self.add_command("abort", operators::abort);
             }
-            None => None,
         }
     }
 
-    fn eval_constants(&self, name: &str, forth: &mut Forth) -> Option<ForthResult<()>> {
-        if let Some(a) = forth.get_constant(&name) {
-            forth.push(a);
-            Some(Ok(()))
-        } else {
-            None
+    fn add_command(&mut self, name: &str, op: fn(&mut Forth) -> ForthResult<()>) {
+        self.register(name, op);
+    }
+
+    // Words that need the interpreter, or read the input, are builtins too,
+    // so that they have execution tokens and are bound when compiled
+    fn add_interpreter_words(&mut self) {
+        let names = [
+            "words", "constant", "variable", "create", "value", "defer", "to", "is", "action-of", "'", "execute",
+            "find", "compile,", "catch", "immediate", "]",
+        ];
+        for name in names {
+            self.commands.push(Builtin {
+                name: name.to_string(),
+                op: None,
+                effect: None,
+                inputs: 0,
+            });
+            self.command_index.insert(name.to_string(), self.commands.len() - 1);
+        }
+    }
+
+    /// Adds a word implemented in Rust, which may own whatever state it
+    /// needs. A builtin of the same name is overridden, except in words
+    /// compiled before, which keep the one they were compiled with.
+    pub fn register<F>(&mut self, name: &str, op: F) -> &mut Builtin
+    where
+        F: Fn(&mut Forth) -> ForthResult<()> + 'static,
+    {
+        let index = self.commands.len();
+        self.command_index.insert(self.key(name).into_owned(), index);
+        self.commands.push(Builtin {
+            name: name.to_string(),
+            op: Some(Box::new(op)),
+            effect: None,
+            inputs: 0,
+        });
+        &mut self.commands[index]
+    }
+
+    /// Like `register`, for words that change their own state when run.
+    pub fn register_mut<F>(&mut self, name: &str, op: F) -> &mut Builtin
+    where
+        F: FnMut(&mut Forth) -> ForthResult<()> + 'static,
+    {
+        let op = RefCell::new(op);
+        self.register(name, move |forth| (op.borrow_mut())(forth))
+    }
+
+    /// Hides a builtin from lookup, returning false if there was none.
+    /// Words already compiled with it still run it.
+    pub fn unregister(&mut self, name: &str) -> bool {
+        let key = self.key(name).into_owned();
+        self.command_index.remove(&key).is_some()
+    }
+
+    /// The builtin a name currently stands for.
+    pub fn builtin(&self, name: &str) -> Option<&Builtin> {
+        self.command_index.get(&*self.key(name)).map(|&index| &self.commands[index])
+    }
+
+    fn eval_commands(&self, op: &str, forth: &mut Forth) -> Option<ForthResult<()>> {
+        self.command_index.get(op).map(|&index| self.call_command(index, forth))
+    }
+
+    fn call_command(&self, index: usize, forth: &mut Forth) -> ForthResult<()> {
+        let builtin = &self.commands[index];
+        let name = &builtin.name;
+        // Fail before the word pops half of its arguments
+        if forth.depth() < builtin.inputs {
+            return Err(ForthError::from(ErrorKind::StackUnderflow).in_word(name).called_from(name));
+        }
+        match &builtin.op {
+            Some(op) => op(forth).map_err(|e| e.in_word(name).called_from(name)),
+            None => self.eval_interpreter_word(name, name, forth),
+        }
+    }
+
+    // Runs a builtin from a running word, leaving any word that EXECUTE or
+    // CATCH starts to the run loop instead of running it here
+    fn enter_command(&self, index: usize, forth: &mut Forth) -> ForthResult<()> {
+        let builtin = &self.commands[index];
+        match builtin.name.as_str() {
+            _ if builtin.op.is_some() => self.call_command(index, forth),
+            "execute" => self.enter_xt(forth),
+            "catch" => self.enter_catch(forth),
+            _ => self.call_command(index, forth),
         }
     }
 
-    pub fn eval_tokens(&self, forth: &mut Forth, tokens: &mut Iter<String>) {
-        while let Some(s) = tokens.next() {
-            if s.trim().is_empty() {
-                continue;
+    fn eval_word(&self, name: &str, forth: &mut Forth) -> Option<ForthResult<()>> {
+        forth.find_word(name).map(|xt| self.execute(forth, xt))
+    }
+
+    // Interprets or compiles the rest of the current line
+    fn eval_source(&self, forth: &mut Forth) -> ForthResult<()> {
+        while let Some((s, column)) = forth.next_word() {
+            let position = Position { line: forth.line(), column };
+            let result = match s.as_str() {
+                // Comments work the same inside and outside definitions
+                "(" => {
+                    forth.parse(b')');
+                    Ok(())
+                }
+                "\\" => {
+                    forth.skip_line();
+                    Ok(())
+                }
+                _ if forth.state() => self.compile(forth, &s),
+                _ => self.eval_top_level(forth, &s),
+            };
+            // A definition with an error in it is dropped altogether
+            if let Err(e) = result {
+                forth.take_definition();
+                forth.set_state(false);
+                return Err(e.at(position));
             }
+        }
+        Ok(())
+    }
 
+    fn eval_top_level(&self, forth: &mut Forth, s: &str) -> ForthResult<()> {
+        let key = self.key(s);
+        match &*key {
             // new word to collect
-            if s.trim() == ":" {
-                match self.check_new_word(tokens) {
-                    Ok(word) => {
-                        println!("New word defined: {:?}", word);
-                        let name = word.0.clone();
-                        forth.add_word(&name, word);
-                        continue;
-                    }
-                    Err(e) => {
-                        println!("Error: {}", e);
-                        break;
-                    }
-                }
+            ":" => self.start_definition(forth),
+
+            //write string
+            ".\"" => {
+                let sentence = self.check_string(forth);
+                forth.write_output(&format!("{}\n", sentence))
             }
 
-            // Print stack
-            if s.trim() == "." {
-                print!("> ");
-                forth.print_stack();
-                continue;
+            "abort\"" => {
+                let message = self.check_string(forth);
+                Self::abort_message(forth, message)
             }
 
-            // Print all words available
-            if s.trim() == "words" {
+            // Control structures only make sense inside a definition
+            _ if Self::is_control_word(&key) => Err(ForthError::from(ErrorKind::CompileOnly).in_word(s)),
+
+            _ => self.eval_token(s, forth),
+        }
+    }
+
+    fn eval_token(&self, s: &str, forth: &mut Forth) -> ForthResult<()> {
+        let key = self.key(s);
+
+        // The old `.` cannot be redefined
+        if key == "." && self.legacy_dot {
+            return self.eval_interpreter_word(&key, s, forth);
+        }
 
-                for command in &self.commands {
-                    let name = command.0.clone();
-                    print!("{} ", name);
+        // Check for new word created
+        if let Some(result) = self.eval_word(&key, forth) {
+            return result;
+        }
+
+        //Check for default commands
+        if let Some(result) = self.eval_commands(&key, forth) {
+            return result;
+        }
+
+        //Check for number
+        let num = Self::parse_number(forth, s)?;
+        forth.push(num);
+        Ok(())
+    }
+
+    // Runs a word the interpreter handles itself
+    fn eval_interpreter_word(&self, key: &str, s: &str, forth: &mut Forth) -> ForthResult<()> {
+        match key {
+            // Print stack
+            "." if self.legacy_dot => forth.write_output("> ").and_then(|_| forth.print_stack()),
+
+            // Print all words available
+            "words" => {
+                let mut names = String::new();
+                let mut visible: Vec<usize> = self.command_index.values().cloned().collect();
+                visible.sort_unstable();
+                for index in visible {
+                    names.push_str(&format!("{} ", self.commands[index].name));
                 }
 
                 for word in forth.get_words() {
-                    let name = word.0.clone();
-                    print!("{} ", name);
+                    names.push_str(&format!("{} ", word.0));
                 }
-                continue;
+                forth.write_output(&names)
             }
 
-            // Create constant
-            if s.trim() == "constant" {
-                match self.create_constant(forth, tokens) {
-                    Ok(_) => {
-                        continue;
-                    }
-                    Err(e) => {
-                        println!("Error: {}", e);
-                        break;
-                    }
+            // Defining words take the name of the new word from the input
+            "constant" => Self::parse_name(s, forth).and_then(|name| self.create_constant(forth, &name)),
+            "variable" => Self::parse_name(s, forth).and_then(|name| self.create_variable(forth, &name)),
+            "create" => Self::parse_name(s, forth).map(|name| {
+                forth.align();
+                let body = forth.here();
+                forth.add_word(&self.key(&name), (name.clone(), WordKind::Created { body, does: None }));
+            }),
+            // Execution token of the next word
+            "'" => self.tick(forth, s).map(|xt| forth.push(xt)),
+            "execute" => {
+                let base = forth.frame_depth();
+                self.enter_xt(forth).and_then(|_| self.run(forth, base))
+            }
+            "find" => self.find_counted(forth),
+            "compile," => self.compile_comma(forth, s),
+            "value" => Self::parse_name(s, forth).and_then(|name| {
+                let value = forth.pop().map_err(|e| e.in_word(s))?;
+                forth.add_word(&self.key(&name), (name.clone(), WordKind::Value(value)));
+                Ok(())
+            }),
+            "defer" => Self::parse_name(s, forth).map(|name| {
+                forth.add_word(&self.key(&name), (name.clone(), WordKind::Deferred(None)));
+            }),
+            "to" => self.parse_value(forth, s).and_then(|xt| Self::store_value(forth, xt)),
+            "is" => self.parse_deferred(forth, s).and_then(|xt| self.store_action(forth, xt)),
+            "action-of" => self.parse_deferred(forth, s).and_then(|xt| Self::push_action(forth, xt)),
+            "catch" => self.catch(forth),
+            "immediate" => {
+                forth.set_immediate();
+                Ok(())
+            }
+            // Back to compiling the definition left by `[`
+            "]" => match forth.definition_mut() {
+                Some(_) => {
+                    forth.set_state(true);
+                    Ok(())
                 }
+                None => Err(ForthError::from(ErrorKind::ControlMismatch).in_word(s)),
+            },
+            _ => Err(ForthError::from(ErrorKind::UndefinedWord).in_word(s)),
+        }
+    }
+
+    // COMPILE, ( xt -- ) appends the word to the open definition
+    fn compile_comma(&self, forth: &mut Forth, s: &str) -> ForthResult<()> {
+        let xt = forth.pop().map_err(|e| e.in_word(s))?;
+        let instruction = self.xt_instruction(forth, xt).map_err(|e| e.in_word(s))?;
+        match forth.definition_mut() {
+            Some(definition) => {
+                definition.code.push(instruction);
+                Ok(())
             }
+            None => Err(ForthError::from(ErrorKind::CompileOnly).in_word(s)),
+        }
+    }
 
-            //write string
-            if s.trim() == ".\"" {
-                match self.check_string(tokens) {
-                    Ok(sentence) => {
-                        println!("{}", sentence);
-                        continue
-                    }
-                    Err(e) => {
-                        println!("Error: {}", e);
-                        break;
-                    }
-                }
+    fn parse_name(word: &str, forth: &mut Forth) -> ForthResult<String> {
+        match forth.next_word() {
+            Some((name, _)) => Ok(name),
+            None => Err(ForthError::from(ErrorKind::MissingName).in_word(word)),
+        }
+    }
+
+    fn builtin_xt(index: usize) -> i32 {
+        -1 - index as i32
+    }
+
+    // Execution token of a name, looked up in the order of eval_token
+    fn find(&self, forth: &Forth, key: &str) -> Option<i32> {
+        match forth.find_word(key) {
+            Some(xt) => Some(xt as i32),
+            None => self.command_index.get(key).map(|&index| Self::builtin_xt(index)),
+        }
+    }
+
+    // Execution token of the next name in the input, as `'` and `[']` take it
+    fn tick(&self, forth: &mut Forth, word: &str) -> ForthResult<i32> {
+        let name = Self::parse_name(word, forth)?;
+        match self.find(forth, &self.key(&name)) {
+            Some(xt) => Ok(xt),
+            None => Err(ForthError::from(ErrorKind::UndefinedWord).in_word(&name)),
+        }
+    }
+
+    fn token(&self, forth: &Forth, xt: i32) -> Option<Token> {
+        if xt < 0 {
+            let index = (-1 - xt) as usize;
+            (index < self.commands.len()).then_some(Token::Builtin(index))
+        } else {
+            forth.word_kind(xt as usize).map(|_| Token::Word(xt as usize))
+        }
+    }
+
+    // The VALUE named next in the input, for TO
+    fn parse_value(&self, forth: &mut Forth, word: &str) -> ForthResult<usize> {
+        self.parse_word_of(forth, word, |kind| matches!(kind, WordKind::Value(_)))
+    }
+
+    // The DEFER named next in the input, for IS and ACTION-OF
+    fn parse_deferred(&self, forth: &mut Forth, word: &str) -> ForthResult<usize> {
+        self.parse_word_of(forth, word, |kind| matches!(kind, WordKind::Deferred(_)))
+    }
+
+    fn parse_word_of(&self, forth: &mut Forth, word: &str, is_kind: fn(&WordKind) -> bool) -> ForthResult<usize> {
+        let name = Self::parse_name(word, forth)?;
+        match forth.find_word(&self.key(&name)) {
+            Some(xt) if forth.word_kind(xt).is_some_and(is_kind) => Ok(xt),
+            Some(_) => Err(ForthError::from(ErrorKind::InvalidName).in_word(&name)),
+            None => Err(ForthError::from(ErrorKind::UndefinedWord).in_word(&name)),
+        }
+    }
+
+    fn store_value(forth: &mut Forth, xt: usize) -> ForthResult<()> {
+        let n = forth.pop().map_err(|e| e.in_word("to"))?;
+        if let Some(WordKind::Value(value)) = forth.word_kind_mut(xt) {
+            *value = n;
+        }
+        Ok(())
+    }
+
+    fn store_action(&self, forth: &mut Forth, xt: usize) -> ForthResult<()> {
+        let action = forth.pop().map_err(|e| e.in_word("is"))?;
+        if self.token(forth, action).is_none() {
+            return Err(ForthError::from(ErrorKind::UndefinedWord).in_word("is"));
+        }
+        // The action must not lead back to the word, which could then never
+        // start running
+        let mut next = Some(action);
+        while let Some(Token::Word(target)) = next.and_then(|action| self.token(forth, action)) {
+            if target == xt {
+                return Err(ForthError::from(ErrorKind::DeferLoop).in_word(forth.word_name(xt)));
             }
+            next = match forth.word_kind(target) {
+                Some(&WordKind::Deferred(action)) => action,
+                _ => None,
+            };
+        }
+        if let Some(WordKind::Deferred(deferred)) = forth.word_kind_mut(xt) {
+            *deferred = Some(action);
+        }
+        Ok(())
+    }
 
-            // Check for new word created
-            match self.eval_word(s, forth) {
-                None => (),
-                Some(Ok(())) => continue,
-                Some(Err(e)) => {
-                    println!("Error: {}", e);
-                    break;
-                }
+    fn push_action(forth: &mut Forth, xt: usize) -> ForthResult<()> {
+        match forth.word_kind(xt) {
+            Some(&WordKind::Deferred(Some(action))) => {
+                forth.push(action);
+                Ok(())
             }
+            _ => Err(ForthError::from(ErrorKind::UndefinedWord).in_word(forth.word_name(xt))),
+        }
+    }
 
-            //Check for default commands
-            match self.eval_commands(s, forth) {
-                None => (),
-                Some(Ok(())) => continue,
-                Some(Err(e)) => {
-                    println!("Error: {}", e);
-                    break;
-                }
+    // Follows deferred words to what they run now. IS never lets the chain
+    // loop, so it always ends.
+    fn resolve_deferred(&self, forth: &Forth, xt: usize) -> ForthResult<Token> {
+        let mut token = Token::Word(xt);
+        while let Token::Word(xt) = token {
+            token = match forth.word_kind(xt) {
+                Some(&WordKind::Deferred(action)) => match action.and_then(|action| self.token(forth, action)) {
+                    Some(action) => action,
+                    None => return Err(ForthError::from(ErrorKind::UndefinedWord).in_word(forth.word_name(xt))),
+                },
+                _ => break,
+            };
+        }
+        Ok(token)
+    }
+
+    // What COMPILE, appends for an execution token
+    fn xt_instruction(&self, forth: &Forth, xt: i32) -> ForthResult<Instruction> {
+        match self.token(forth, xt) {
+            Some(Token::Word(xt)) => Ok(Instruction::Call(xt)),
+            Some(Token::Builtin(index)) => Ok(Instruction::Builtin(index)),
+            None => Err(ErrorKind::UndefinedWord.into()),
+        }
+    }
+
+    // FIND ( c-addr -- c-addr 0 | xt 1 | xt -1 ) looks up a counted string,
+    // giving 1 for immediate words
+    fn find_counted(&self, forth: &mut Forth) -> ForthResult<()> {
+        let addr = forth.pop().map_err(|e| e.in_word("find"))?;
+        let len = forth.fetch_char(addr).map_err(|e| e.in_word("find"))?;
+        let name = forth.string_at(addr + 1, len as i32).map_err(|e| e.in_word("find"))?;
+        match self.find(forth, &self.key(&name)) {
+            Some(xt) => {
+                let immediate = usize::try_from(xt).is_ok_and(|xt| forth.is_immediate(xt));
+                forth.push(xt);
+                forth.push(if immediate { 1 } else { -1 });
+            }
+            None => {
+                forth.push(addr);
+                forth.push(0);
             }
+        }
+        Ok(())
+    }
 
-            //Check for constants
-            match self.eval_constants(s, forth) {
-                None => (),
-                Some(Ok(())) => continue,
-                Some(Err(e)) => {
-                    println!("Error: {}", e);
-                    break;
-                }
+    // Starts the execution token on the stack. Builtins run to the end at
+    // once, colon definitions only get a frame.
+    fn enter_xt(&self, forth: &mut Forth) -> ForthResult<()> {
+        let xt = forth.pop().map_err(|e| e.in_word("execute"))?;
+        match self.token(forth, xt) {
+            Some(Token::Builtin(index)) => self.enter_command(index, forth),
+            Some(Token::Word(xt)) => self.enter(forth, xt, None).map(|_| ()),
+            None => Err(ForthError::from(ErrorKind::UndefinedWord).in_word("execute")),
+        }
+    }
+
+    /// Runs the word whose execution token is on the stack, then pushes 0,
+    /// or the throw code after restoring both stack depths if it failed.
+    fn catch(&self, forth: &mut Forth) -> ForthResult<()> {
+        let base = forth.frame_depth();
+        self.enter_catch(forth)?;
+        self.run(forth, base)
+    }
+
+    // Starts the execution token on the stack so that whatever it throws is
+    // pushed as a code instead of unwinding further
+    fn enter_catch(&self, forth: &mut Forth) -> ForthResult<()> {
+        let xt = forth.pop().map_err(|e| e.in_word("catch"))?;
+        let depth = forth.depth();
+        let return_depth = forth.return_depth();
+        let token = match self.token(forth, xt) {
+            Some(Token::Word(xt)) => self.resolve_deferred(forth, xt),
+            Some(token) => Ok(token),
+            None => return Err(ForthError::from(ErrorKind::UndefinedWord).in_word("catch")),
+        };
+        // Builtins, and failures to even start a word, are over at once
+        let entered = token.and_then(|token| match token {
+            Token::Word(xt) => self.enter(forth, xt, Some(depth)),
+            Token::Builtin(index) => self.call_command(index, forth).map(|_| false),
+        });
+        match entered {
+            Ok(true) => (),
+            Ok(false) => forth.push(0),
+            Err(e) => {
+                forth.set_depth(depth);
+                forth.truncate_return(return_depth);
+                forth.push(e.code());
             }
+        }
+        Ok(())
+    }
 
-            //Check for number
-            match s.parse::<i32>() {
-                Ok(num) => forth.push(num),
-                Err(e) => {
-                    println!("Error: {}", e);
-                    break;
+    fn abort_message(forth: &mut Forth, message: String) -> ForthResult<()> {
+        if forth.pop().map_err(|e| e.in_word("abort\""))? != 0 {
+            return Err(ErrorKind::AbortMessage(message).into());
+        }
+        Ok(())
+    }
+
+    /// Runs the dictionary entry with execution token `xt`.
+    fn execute(&self, forth: &mut Forth, xt: usize) -> ForthResult<()> {
+        let base = forth.frame_depth();
+        self.enter(forth, xt, None)?;
+        self.run(forth, base)
+    }
+
+    // Starts the word `xt`. Constants and plain CREATEd words finish at
+    // once, anything with code gets a frame and returns true.
+    fn enter(&self, forth: &mut Forth, xt: usize, catch_depth: Option<usize>) -> ForthResult<bool> {
+        let xt = match self.resolve_deferred(forth, xt)? {
+            Token::Word(xt) => xt,
+            Token::Builtin(index) => {
+                self.enter_command(index, forth)?;
+                return Ok(false);
+            }
+        };
+        let kind = match forth.word_kind(xt) {
+            Some(kind) => kind.clone(),
+            None => return Err(ErrorKind::UndefinedWord.into()),
+        };
+        let mut frame = match kind {
+            WordKind::Colon(body) => Frame::new(xt, xt, body, 0),
+            WordKind::Constant(value) | WordKind::Value(value) => {
+                forth.push(value);
+                return Ok(false);
+            }
+            WordKind::Deferred(_) => unreachable!("deferred word was not resolved"),
+            WordKind::Created { body, does } => {
+                forth.push(body as i32);
+                match does.map(|(does_xt, start)| (does_xt, start, forth.word_kind(does_xt))) {
+                    Some((does_xt, start, Some(WordKind::Colon(code)))) => {
+                        Frame::new(xt, does_xt, code.clone(), start)
+                    }
+                    _ => return Ok(false),
                 }
             }
+        };
+        frame.catch_depth = catch_depth;
+        forth.push_frame(frame).map_err(|e| e.in_word(forth.word_name(xt)))?;
+        Ok(true)
+    }
+
+    // Runs frames until the call stack is back down to `base`. The call
+    // stack lives in Forth, so calls between words never recurse here.
+    fn run(&self, forth: &mut Forth, base: usize) -> ForthResult<()> {
+        while forth.frame_depth() > base {
+            if let Err(e) = self.step(forth) {
+                self.unwind(forth, base, e)?;
+            }
         }
+        Ok(())
     }
 
+    // Pops frames down to the innermost CATCH above `base`, which gets the
+    // error code. Without one, the error is returned with its backtrace.
+    fn unwind(&self, forth: &mut Forth, base: usize, mut e: ForthError) -> ForthResult<()> {
+        while forth.frame_depth() > base {
+            let frame = match forth.pop_frame() {
+                Some(frame) => frame,
+                None => break,
+            };
+            // Never hand a corrupted return stack back to the caller
+            forth.truncate_return(frame.return_depth());
+            e = e.called_from(forth.word_name(frame.xt));
+            if let Some(depth) = frame.catch_depth {
+                forth.set_depth(depth);
+                forth.push(e.code());
+                return Ok(());
+            }
+        }
+        Err(e)
+    }
 
-    fn check_new_word(&self, tokens: &mut Iter<String>) -> ForthResult<ForthWord> {
-        if let Some(name) = tokens.next() {
-            if !Self::valid_word_name(name) {
-                return Err(format!("Invalid name for function: {}", name));
+    // Leaves the running word, which must give back the return stack as it
+    // found it
+    fn leave(&self, forth: &mut Forth) -> ForthResult<()> {
+        if let Some(frame) = forth.current_frame() {
+            let xt = frame.xt;
+            if frame.return_depth() != forth.return_depth() {
+                return Err(ForthError::from(ErrorKind::ReturnStackImbalance).in_word(forth.word_name(xt)));
+            }
+        }
+        if let Some(Frame { catch_depth: Some(_), .. }) = forth.pop_frame() {
+            forth.push(0);
+        }
+        Ok(())
+    }
+
+    // Runs the next instruction of the innermost frame
+    fn step(&self, forth: &mut Forth) -> ForthResult<()> {
+        let (body, current, owner) = match forth.current_frame() {
+            Some(frame) => {
+                frame.pc += 1;
+                (frame.body.clone(), frame.pc - 1, frame.owner)
+            }
+            None => return Ok(()),
+        };
+        let instruction = match body.get(current) {
+            Some(instruction) => instruction,
+            None => return self.leave(forth),
+        };
+        let mut jump = None;
+        match instruction {
+            Instruction::Literal(num) => forth.push(*num),
+            Instruction::Builtin(index) => self.enter_command(*index, forth)?,
+            Instruction::Call(xt) => {
+                self.enter(forth, *xt, None)?;
+            }
+            Instruction::Recurse => {
+                self.enter(forth, owner, None)?;
+            }
+            Instruction::Compile(name) => self.compile_word(forth, name)?,
+            Instruction::To(xt) => Self::store_value(forth, *xt)?,
+            Instruction::Is(xt) => self.store_action(forth, *xt)?,
+            Instruction::ActionOf(xt) => Self::push_action(forth, *xt)?,
+            Instruction::Word(name) => self.eval_interpreter_word(name, name, forth)?,
+            Instruction::Print(sentence) => forth.write_output(&format!("{}\n", sentence))?,
+            Instruction::Branch(target) => jump = Some(*target),
+            Instruction::BranchIfZero(target) => {
+                if forth.pop()? == 0 {
+                    jump = Some(*target);
+                }
+            }
+            Instruction::Do => {
+                let (limit, index) = Self::pop_loop_range(forth).map_err(|e| e.in_word("do"))?;
+                forth.push_return(limit);
+                forth.push_return(index);
             }
-            let mut definition: Vec<String> = vec![];
-            for s in tokens {
-                if s == ";" {
-                    return Ok((name.to_string(), definition));
+            Instruction::QuestionDo(target) => {
+                let (limit, index) = Self::pop_loop_range(forth).map_err(|e| e.in_word("?do"))?;
+                if limit == index {
+                    jump = Some(*target);
                 } else {
-                    definition.push(s.to_string());
+                    forth.push_return(limit);
+                    forth.push_return(index);
                 }
             }
+            Instruction::Loop(start) => {
+                if !Self::step_loop(forth, 1)? {
+                    jump = Some(*start);
+                }
+            }
+            Instruction::PlusLoop(start) => {
+                let step = forth.pop().map_err(|e| e.in_word("+loop"))?;
+                if !Self::step_loop(forth, step)? {
+                    jump = Some(*start);
+                }
+            }
+            Instruction::Leave(target) => {
+                operators_return::unloop(forth)?;
+                jump = Some(*target);
+            }
+            Instruction::Exit => return self.leave(forth),
+            Instruction::AbortMessage(message) => Self::abort_message(forth, message.clone())?,
+            // The rest of the body becomes the behavior of the last CREATEd word
+            Instruction::Does => {
+                forth.set_does(owner, current + 1)?;
+                return self.leave(forth);
+            }
+        }
+        if let Some(target) = jump {
+            if let Some(frame) = forth.current_frame() {
+                frame.pc = target;
+            }
+            // Every loop jumps backwards, so this is where a host can stop us
+            if target <= current {
+                forth.check_interrupt()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pop_loop_range(forth: &mut Forth) -> ForthResult<(i32, i32)> {
+        let index = forth.pop()?;
+        let limit = forth.pop()?;
+        Ok((limit, index))
+    }
+
+    // Add `step` to the innermost loop index. Returns true, after discarding
+    // the loop parameters, once the index crosses the limit-1/limit boundary.
+    fn step_loop(forth: &mut Forth, step: i32) -> ForthResult<bool> {
+        let index = forth.pop_return()?;
+        let limit = forth.peek_return(0)?;
+        let offset = index.wrapping_sub(limit);
+        let (next, overflow) = offset.overflowing_add(step);
+        if (offset ^ next) < 0 && !overflow {
+            forth.pop_return()?;
+            Ok(true)
+        } else {
+            forth.push_return(index.wrapping_add(step));
+            Ok(false)
+        }
+    }
+
+    // Opens a definition named by the next name in the input; what follows,
+    // on this line or the next ones, is compiled into it until `;`
+    fn start_definition(&self, forth: &mut Forth) -> ForthResult<()> {
+        let (name, column) = match forth.next_word() {
+            Some(word) => word,
+            None => return Err(ForthError::from(ErrorKind::MissingName).in_word(":")),
+        };
+        if !Self::valid_word_name(forth, &name) {
+            let position = Position { line: forth.line(), column };
+            return Err(ForthError::from(ErrorKind::InvalidName).in_word(&name).at(position));
+        }
+        forth.start_definition(Definition::new(&name));
+        Ok(())
+    }
+
+    fn compile(&self, forth: &mut Forth, s: &str) -> ForthResult<()> {
+        // Immediate words run right away, and may compile into the open
+        // definition themselves
+        if let Some(xt) = forth.find_word(&self.key(s)).filter(|&xt| forth.is_immediate(xt)) {
+            return self.execute(forth, xt);
         }
-        Err("Invalid function".to_string())
+        self.compile_word(forth, s)
     }
 
-    fn check_string(&self, tokens: &mut Iter<String>) -> ForthResult<String> {
-        let mut sentence = String::new();
-        for s in tokens {
-            if s == "\"" {
-                return Ok(sentence);
+    // Compiles `s` into the open definition, ending it at `;`
+    fn compile_word(&self, forth: &mut Forth, s: &str) -> ForthResult<()> {
+        let mut definition = match forth.take_definition() {
+            Some(definition) => definition,
+            None => return Err(ForthError::from(ErrorKind::ControlMismatch).in_word(s)),
+        };
+        if self.compile_into(forth, &mut definition, s)? {
+            forth.set_state(false);
+            let word = (definition.name.clone(), WordKind::Colon(Rc::from(definition.code)));
+            forth.add_word(&self.key(&definition.name), word);
+        } else {
+            forth.restore_definition(definition);
+        }
+        Ok(())
+    }
+
+    // Compiles the compilation semantics of `name`: immediate words and the
+    // words compiled by the interpreter itself do their work when the
+    // definition runs, anything else is compiled by it.
+    fn postpone(&self, forth: &Forth, code: &mut Vec<Instruction>, name: &str) -> ForthResult<()> {
+        let key = self.key(name);
+        match forth.find_word(&key) {
+            Some(xt) if forth.is_immediate(xt) => code.push(Instruction::Call(xt)),
+            _ if Self::is_compiler_word(&key) => code.push(Instruction::Compile(key.into_owned())),
+            _ => match self.find(forth, &key) {
+                Some(xt) => {
+                    code.push(Instruction::Literal(xt));
+                    code.push(Instruction::Word("compile,".to_string()));
+                }
+                None => return Err(ForthError::from(ErrorKind::UndefinedWord).in_word(name)),
+            },
+        }
+        Ok(())
+    }
+
+    // Compiles one token into `definition`, returning true once `;` ends it
+    fn compile_into(&self, forth: &mut Forth, definition: &mut Definition, s: &str) -> ForthResult<bool> {
+        let mismatch = || ForthError::from(ErrorKind::ControlMismatch).in_word(s);
+        let Definition { code, control, .. } = definition;
+        let key = self.key(s);
+        match &*key {
+            ";" => {
+                if let Some(open) = control.last() {
+                    return Err(ForthError::from(ErrorKind::ControlMismatch).in_word(open.name()));
+                }
+                return Ok(true);
             }
-            if s.chars().last().unwrap() == '"' {
-                let mut temp = s.clone();
-                temp.pop();
-                sentence.push_str(&temp.clone());
-                return Ok(sentence);
+            "if" => {
+                control.push(Control::If(code.len()));
+                code.push(Instruction::BranchIfZero(0));
+            }
+            "else" => match control.pop() {
+                Some(Control::If(orig)) => {
+                    control.push(Control::Else(code.len()));
+                    code.push(Instruction::Branch(0));
+                    Self::resolve(code, orig);
+                }
+                _ => return Err(mismatch()),
+            },
+            "then" => match control.pop() {
+                Some(Control::If(orig)) | Some(Control::Else(orig)) => Self::resolve(code, orig),
+                _ => return Err(mismatch()),
+            },
+            "do" => {
+                code.push(Instruction::Do);
+                control.push(Control::Do { start: code.len(), leaves: vec![] });
             }
-            sentence.push_str(&format!("{} ", s));
+            "?do" => {
+                let orig = code.len();
+                code.push(Instruction::QuestionDo(0));
+                control.push(Control::Do { start: code.len(), leaves: vec![orig] });
+            }
+            "loop" | "+loop" => match control.pop() {
+                Some(Control::Do { start, leaves }) => {
+                    code.push(if key == "loop" {
+                        Instruction::Loop(start)
+                    } else {
+                        Instruction::PlusLoop(start)
+                    });
+                    for orig in leaves {
+                        Self::resolve(code, orig);
+                    }
+                }
+                _ => return Err(mismatch()),
+            },
+            "leave" => {
+                let orig = code.len();
+                match control.iter_mut().rev().find(|c| matches!(c, Control::Do { .. })) {
+                    Some(Control::Do { leaves, .. }) => leaves.push(orig),
+                    _ => return Err(mismatch()),
+                }
+                code.push(Instruction::Leave(0));
+            }
+            "begin" => control.push(Control::Begin(code.len())),
+            "until" | "again" => match control.pop() {
+                Some(Control::Begin(dest)) => code.push(if key == "until" {
+                    Instruction::BranchIfZero(dest)
+                } else {
+                    Instruction::Branch(dest)
+                }),
+                _ => return Err(mismatch()),
+            },
+            "while" => match control.pop() {
+                Some(Control::Begin(dest)) => {
+                    control.push(Control::While { dest, orig: code.len() });
+                    code.push(Instruction::BranchIfZero(0));
+                }
+                _ => return Err(mismatch()),
+            },
+            "repeat" => match control.pop() {
+                Some(Control::While { dest, orig }) => {
+                    code.push(Instruction::Branch(dest));
+                    Self::resolve(code, orig);
+                }
+                _ => return Err(mismatch()),
+            },
+            "exit" => code.push(Instruction::Exit),
+            "recurse" => code.push(Instruction::Recurse),
+            "[']" => code.push(Instruction::Literal(self.tick(forth, s)?)),
+            "[char]" => {
+                let name = Self::parse_name(s, forth)?;
+                let c = name.chars().next().map_or(0, |c| c as i32);
+                code.push(Instruction::Literal(c));
+            }
+            "literal" => code.push(Instruction::Literal(forth.pop().map_err(|e| e.in_word(s))?)),
+            "postpone" => {
+                let name = Self::parse_name(s, forth)?;
+                self.postpone(forth, code, &name)?;
+            }
+            // The word is looked up once, when the definition is compiled
+            "to" => code.push(Instruction::To(self.parse_value(forth, s)?)),
+            "is" => code.push(Instruction::Is(self.parse_deferred(forth, s)?)),
+            "action-of" => code.push(Instruction::ActionOf(self.parse_deferred(forth, s)?)),
+            // Interpret the rest of the definition until `]`
+            "[" => forth.set_state(false),
+            ".\"" => code.push(Instruction::Print(self.check_string(forth))),
+            "abort\"" => code.push(Instruction::AbortMessage(self.check_string(forth))),
+            "does>" => code.push(Instruction::Does),
+            _ => code.push(self.compile_token(forth, s)?),
         }
+        Ok(false)
+    }
 
-        Err("Invalid string".to_string())
+    // Bind a token to what its name means right now, following the lookup
+    // order of eval_token
+    fn compile_token(&self, forth: &Forth, s: &str) -> ForthResult<Instruction> {
+        let key = self.key(s);
+        if key == "." && self.legacy_dot {
+            return Ok(Instruction::Word(key.into_owned()));
+        }
+        if let Some(xt) = forth.find_word(&key) {
+            return Ok(Instruction::Call(xt));
+        }
+        if let Some(&index) = self.command_index.get(&*key) {
+            return Ok(Instruction::Builtin(index));
+        }
+        Self::parse_number(forth, s).map(Instruction::Literal)
     }
 
-    fn create_constant(&self, forth: &mut Forth, tokens: &mut Iter<String>) -> ForthResult<()> {
-        if let Some(const_name) = tokens.next() {
-            let a = forth.pop(format!("Stack empty to set constant {}", const_name))?;
-            forth.add_constant(const_name, a);
-            Ok(())
-        } else {
-            Err("Const name not found".to_string())
+    // Point the forward branch at `orig` to the end of the definition so far
+    fn resolve(definition: &mut [Instruction], orig: usize) {
+        let target = definition.len();
+        match &mut definition[orig] {
+            Instruction::Branch(dest)
+            | Instruction::BranchIfZero(dest)
+            | Instruction::QuestionDo(dest)
+            | Instruction::Leave(dest) => *dest = target,
+            _ => unreachable!("control structure does not point at a branch"),
         }
     }
 
-    fn valid_word_name(name: &str) -> bool {
-        name.parse::<i32>().is_err()
+    fn is_control_word(name: &str) -> bool {
+        matches!(
+            name,
+            "if" | "else"
+                | "then"
+                | "do"
+                | "?do"
+                | "loop"
+                | "+loop"
+                | "leave"
+                | "begin"
+                | "until"
+                | "while"
+                | "repeat"
+                | "again"
+                | "exit"
+                | "recurse"
+                | "[']"
+                | "[char]"
+                | "literal"
+                | "postpone"
+                | "does>"
+        )
+    }
+
+    // Words that compile_into handles itself rather than looking them up
+    fn is_compiler_word(name: &str) -> bool {
+        Self::is_control_word(name) || matches!(name, ";" | "[" | ".\"" | "abort\"" | "to" | "is" | "action-of")
     }
-    
+
+    // The text up to the closing quote, which may be left out at the end
+    // of the line
+    fn check_string(&self, forth: &mut Forth) -> String {
+        let (addr, len) = forth.parse(b'"');
+        forth.text(addr, len)
+    }
+
+    fn create_constant(&self, forth: &mut Forth, const_name: &str) -> ForthResult<()> {
+        let a = forth.pop().map_err(|e| e.in_word("constant"))?;
+        forth.add_word(&self.key(const_name), (const_name.to_string(), WordKind::Constant(a)));
+        Ok(())
+    }
+
+    // A variable is a word pushing the address of a fresh, zeroed cell
+    fn create_variable(&self, forth: &mut Forth, var_name: &str) -> ForthResult<()> {
+        forth.align();
+        let addr = forth.here() as i32;
+        forth.allot(CELL_SIZE as i32).map_err(|e| e.in_word("variable"))?;
+        forth.store(addr, 0)?;
+        forth.add_word(&self.key(var_name), (var_name.to_string(), WordKind::Created { body: addr as usize, does: None }));
+        Ok(())
+    }
+
+    fn valid_word_name(forth: &Forth, name: &str) -> bool {
+        Self::parse_number(forth, name).is_err()
+    }
+
+    // Parses a number in BASE, or in the base given by a `#`, `$` or `%`
+    // prefix. Like output, it falls back to decimal when BASE makes no
+    // sense. `'c'` stands for the code of the character c. Anything that
+    // neither starts with a prefix nor a digit is taken for a missing word.
+    fn parse_number(forth: &Forth, s: &str) -> ForthResult<i32> {
+        let mut chars = s.chars();
+        if let (Some('\''), Some(c), Some('\''), None) = (chars.next(), chars.next(), chars.next(), chars.next()) {
+            return Ok(c as i32);
+        }
+        let (base, rest) = match s.chars().next() {
+            Some('#') => (10, &s[1..]),
+            Some('$') => (16, &s[1..]),
+            Some('%') => (2, &s[1..]),
+            _ => (forth.radix(), s),
+        };
+        let (negative, digits) = match rest.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, rest.strip_prefix('+').unwrap_or(rest)),
+        };
+        let value = digits.chars().try_fold(0u32, |value, c| {
+            let digit = c.to_digit(36).filter(|&digit| digit < base)?;
+            value.checked_mul(base)?.checked_add(digit)
+        });
+        let prefixed = rest.len() < s.len();
+        match value {
+            _ if digits.is_empty() => Err(ForthError::from(ErrorKind::UndefinedWord).in_word(s)),
+            // Numbers fill a whole cell, so $FFFFFFFF is -1
+            Some(value) if negative => Ok((value as i32).wrapping_neg()),
+            Some(value) => Ok(value as i32),
+            None if prefixed || digits.starts_with(|c: char| c.is_ascii_digit()) => {
+                Err(ForthError::from(ErrorKind::InvalidNumber(base)).in_word(s))
+            }
+            None => Err(ForthError::from(ErrorKind::UndefinedWord).in_word(s)),
+        }
+    }
+
 }
\ No newline at end of file
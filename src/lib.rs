@@ -0,0 +1,1301 @@
+//! A small Forth interpreter.
+//!
+//! A [`Forth`] holds the stacks, the dictionary and the data space, and an
+//! [`Interpreter`] reads source text into it:
+//!
+//! ```
+//! use ruforth::{Forth, Interpreter};
+//!
+//! let (mut forth, output, _) = Forth::buffered();
+//! let intr = Interpreter::new();
+//! intr.eval(&mut forth, ": square dup * ;").unwrap();
+//! intr.eval(&mut forth, "7 square .").unwrap();
+//! assert_eq!("49 ", output.contents());
+//! ```
+
+pub mod error;
+pub mod forth;
+pub mod interpreter;
+mod operators;
+mod operators_binary;
+mod operators_input;
+mod operators_memory;
+mod operators_output;
+mod operators_return;
+
+pub use error::{ErrorKind, ForthError, Position};
+pub use forth::{Forth, ForthResult, SharedBuffer};
+pub use interpreter::{Builtin, Interpreter, InterpreterBuilder, WordSet};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![5];
+        intr.eval(&mut forth, "2 3 +").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_sub() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-1];
+        intr.eval(&mut forth, "2 3 -").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_mul() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![6];
+        intr.eval(&mut forth, "2 3 *").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_div() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![2];
+        intr.eval(&mut forth, "6 3 /").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_mod() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0];
+        intr.eval(&mut forth, "3 3 mod").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_dup() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![3, 3];
+        intr.eval(&mut forth, "3 dup").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_swap() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![2, 3];
+        intr.eval(&mut forth, "3 2 swap").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_negate() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-99];
+        intr.eval(&mut forth, "99 negate").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_abs_negative() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![99];
+        intr.eval(&mut forth, "-99 abs").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_abs_positive() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![99];
+        intr.eval(&mut forth, "99 abs").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_rot() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![4, 5, 6];
+        intr.eval(&mut forth, "6 4 5 rot").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_drop() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![2];
+        intr.eval(&mut forth, "2 3 drop").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_nip() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![1, 3];
+        intr.eval(&mut forth, "1 2 3 nip").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_tuck() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![1, 2, 4, 3, 4];
+        intr.eval(&mut forth, "1 2 3 4 tuck").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_over() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![1, 2, 3, 4, 3];
+        intr.eval(&mut forth, "1 2 3 4 over").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_max() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![3];
+        intr.eval(&mut forth, "1 3 max").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_min() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![1];
+        intr.eval(&mut forth, "1 3 min").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_clearstack() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec : Vec<i32> = Vec::new();
+        intr.eval(&mut forth, "1 3 4 + clearstack").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_add_word() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![25];
+        intr.eval(&mut forth, ": square dup * ;").unwrap();
+        intr.eval(&mut forth, "5 square").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_add_constant() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![100];
+        intr.eval(&mut forth, "100 constant HUNDRED").unwrap();
+        intr.eval(&mut forth, "HUNDRED").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_use_constant() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![125];
+        intr.eval(&mut forth, "100 constant HUNDRED").unwrap();
+        intr.eval(&mut forth, "HUNDRED 25 +").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_multiple_commands() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![14];
+        intr.eval(&mut forth, "2 3 4 * +").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_few_stack() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec : Vec<i32> = Vec::new();
+        let err = intr.eval(&mut forth, "2 +").unwrap_err();
+        assert_eq!(error::ErrorKind::StackUnderflow, err.kind);
+        assert_eq!(Some("+".to_string()), err.word);
+        assert_eq!(Some(error::Position { line: 1, column: 3 }), err.position);
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_equals_1() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-1];
+        intr.eval(&mut forth, "2 2 =").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_equals_2() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0];
+        intr.eval(&mut forth, "2 3 =").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_not_equals_1() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-1];
+        intr.eval(&mut forth, "2 3 !=").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_not_equals_2() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0];
+        intr.eval(&mut forth, "2 2 !=").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_greater() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-1];
+        intr.eval(&mut forth, "4 2 >").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_less() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-1];
+        intr.eval(&mut forth, "2 4 <").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_greater_equals() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-1];
+        intr.eval(&mut forth, "2 2 >=").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_less_equals() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-1];
+        intr.eval(&mut forth, "4 4 <=").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_invert() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-1];
+        intr.eval(&mut forth, "0 invert").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_if_then() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![10, 1];
+        intr.eval(&mut forth, ": positive 0 > if 1 then ;").unwrap();
+        intr.eval(&mut forth, "10 dup positive -10 positive").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_if_else_then() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![1, 2];
+        intr.eval(&mut forth, ": pick 0 = if 1 else 2 then ;").unwrap();
+        intr.eval(&mut forth, "0 pick 5 pick").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_nested_if() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-1, 0, 1];
+        intr.eval(&mut forth, ": sign dup 0 < if drop -1 else 0 > if 1 else 0 then then ;").unwrap();
+        intr.eval(&mut forth, "-5 sign 0 sign 5 sign").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_unbalanced_if() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec: Vec<i32> = Vec::new();
+        let err = intr.eval(&mut forth, ": broken 1 if 2 ;").unwrap_err();
+        assert_eq!(error::ErrorKind::ControlMismatch, err.kind);
+        assert_eq!(-22, err.code());
+        let err = intr.eval(&mut forth, ": broken2 1 then ;").unwrap_err();
+        assert_eq!(Some("then".to_string()), err.word);
+        assert_eq!(Some(error::Position { line: 2, column: 13 }), err.position);
+        assert!(forth.get_word("broken").is_none());
+        assert!(forth.get_word("broken2").is_none());
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_do_loop() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![45];
+        intr.eval(&mut forth, ": sum 0 10 0 do i + loop ;").unwrap();
+        intr.eval(&mut forth, "sum").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_nested_do_loop() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0, 1, 10, 11];
+        intr.eval(&mut forth, ": grid 2 0 do 2 0 do j 10 * i + loop loop ;").unwrap();
+        intr.eval(&mut forth, "grid").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_question_do() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0, 3];
+        intr.eval(&mut forth, ": count 0 swap 0 ?do 1 + loop ;").unwrap();
+        intr.eval(&mut forth, "0 count 3 count").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_plus_loop() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0, 3, 6, 9, 2, 1, 0];
+        intr.eval(&mut forth, ": up 10 0 do i 3 +loop ;").unwrap();
+        intr.eval(&mut forth, ": down 0 2 do i -1 +loop ;").unwrap();
+        intr.eval(&mut forth, "up down").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_leave() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0, 1, 2, 3];
+        intr.eval(&mut forth, ": upto3 10 0 do i dup 3 = if leave then loop ;").unwrap();
+        intr.eval(&mut forth, "upto3").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_exit_inside_loop() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0, 2, 2];
+        intr.eval(&mut forth, ": first-even 10 swap do i 2 mod 0 = if i unloop exit then loop -1 ;").unwrap();
+        intr.eval(&mut forth, ": evens 3 0 do i first-even loop ;").unwrap();
+        intr.eval(&mut forth, "evens").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_begin_until() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![3, 2, 1];
+        intr.eval(&mut forth, ": countdown begin dup 1 - dup 1 = until ;").unwrap();
+        intr.eval(&mut forth, "3 countdown").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_begin_while_repeat() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![64];
+        intr.eval(&mut forth, ": double-until begin dup 50 < while 2 * repeat ;").unwrap();
+        intr.eval(&mut forth, "1 double-until").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_begin_again_exit() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![10];
+        intr.eval(&mut forth, ": to-ten begin 1 + dup 10 = if exit then again ;").unwrap();
+        intr.eval(&mut forth, "0 to-ten").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_interrupt_loop() {
+        use std::sync::atomic::Ordering;
+
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let interrupt = forth.interrupt_handle();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            interrupt.store(true, Ordering::SeqCst);
+        });
+        intr.eval(&mut forth, ": forever begin again ;").unwrap();
+        let err = intr.eval(&mut forth, "forever").unwrap_err();
+        stopper.join().unwrap();
+        assert_eq!(error::ErrorKind::Interrupted, err.kind);
+        assert_eq!(Vec::<i32>::new(), forth.get_stack());
+    }
+
+    #[test]
+    fn test_return_stack_transfer() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![2, 1, 3, 3, 1, 2];
+        intr.eval(&mut forth, ": under >r swap r@ drop r> ;").unwrap();
+        intr.eval(&mut forth, "1 2 3 under").unwrap();
+        intr.eval(&mut forth, ": sum-keep 2>r 2r@ + 2r> 7 >r rdrop ;").unwrap();
+        intr.eval(&mut forth, "1 2 sum-keep").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_return_stack_unbalanced() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec: Vec<i32> = Vec::new();
+        intr.eval(&mut forth, ": leak 7 >r ;").unwrap();
+        intr.eval(&mut forth, ": steal r> ;").unwrap();
+        intr.eval(&mut forth, ": early-exit 7 >r exit ;").unwrap();
+        intr.eval(&mut forth, ": caller 1 0 do steal loop ;").unwrap();
+        let err = intr.eval(&mut forth, "leak").unwrap_err();
+        assert_eq!(error::ErrorKind::ReturnStackImbalance, err.kind);
+        assert_eq!(Some("leak".to_string()), err.word);
+        let err = intr.eval(&mut forth, "early-exit").unwrap_err();
+        assert_eq!(error::ErrorKind::ReturnStackImbalance, err.kind);
+        let err = intr.eval(&mut forth, "caller").unwrap_err();
+        assert_eq!(error::ErrorKind::ReturnStackUnderflow, err.kind);
+        assert_eq!(Some("r>".to_string()), err.word);
+        assert_eq!(vec, forth.get_stack());
+        assert_eq!(0, forth.return_depth());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let err = intr.eval(&mut forth, "1 foo").unwrap_err();
+        assert_eq!(error::ErrorKind::UndefinedWord, err.kind);
+        assert_eq!(-13, err.code());
+        assert_eq!(Some("foo".to_string()), err.word);
+        let err = intr.eval(&mut forth, "12x").unwrap_err();
+        assert_eq!(error::ErrorKind::InvalidNumber(10), err.kind);
+        let err = intr.eval(&mut forth, "1 0 /").unwrap_err();
+        assert_eq!(-10, err.code());
+        let err = intr.eval(&mut forth, "1 if").unwrap_err();
+        assert_eq!(error::ErrorKind::CompileOnly, err.kind);
+        assert_eq!("interpreting a compile-only word (-14) in if at line 4, column 3", err.to_string());
+    }
+
+    #[test]
+    fn test_catch() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![1, 2, 3, 0, 1, 2, -10];
+        intr.eval(&mut forth, ": safe 3 ;").unwrap();
+        intr.eval(&mut forth, ": risky 5 6 7 1 0 / ;").unwrap();
+        intr.eval(&mut forth, "1 2 ' safe catch").unwrap();
+        intr.eval(&mut forth, "1 2 ' risky catch").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_throw() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![7, 7, 42, 0];
+        intr.eval(&mut forth, ": fail >r dup 42 throw ;").unwrap();
+        intr.eval(&mut forth, ": no-fail 0 throw ;").unwrap();
+        intr.eval(&mut forth, "7 1 ' fail catch ' no-fail catch").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        assert_eq!(0, forth.return_depth());
+        let err = intr.eval(&mut forth, "-10 throw").unwrap_err();
+        assert_eq!(error::ErrorKind::DivisionByZero, err.kind);
+        let err = intr.eval(&mut forth, "99 throw").unwrap_err();
+        assert_eq!(error::ErrorKind::Throw(99), err.kind);
+    }
+
+    #[test]
+    fn test_abort() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-2, -1];
+        intr.eval(&mut forth, ": check 0 < abort\" negative value\" ;").unwrap();
+        intr.eval(&mut forth, ": check-neg -5 check ;").unwrap();
+        intr.eval(&mut forth, ": stop abort ;").unwrap();
+        intr.eval(&mut forth, "1 check ' check-neg catch ' stop catch").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        let err = intr.eval(&mut forth, "check-neg").unwrap_err();
+        assert_eq!(error::ErrorKind::AbortMessage("negative value".to_string()), err.kind);
+        assert_eq!(Vec::<i32>::new(), forth.get_stack());
+    }
+
+    #[test]
+    fn test_error_in_nested_word() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec: Vec<i32> = Vec::new();
+        intr.eval(&mut forth, ": square dup * ;").unwrap();
+        intr.eval(&mut forth, ": middle square 1 + ;").unwrap();
+        intr.eval(&mut forth, ": outer middle 2 ;").unwrap();
+        let err = intr.eval(&mut forth, "outer 3 4").unwrap_err();
+        assert_eq!(error::ErrorKind::StackUnderflow, err.kind);
+        assert_eq!(Some("dup".to_string()), err.word);
+        assert_eq!(Some(error::Position { line: 4, column: 1 }), err.position);
+        assert_eq!(vec!["outer", "middle", "square", "dup"], err.backtrace);
+        assert_eq!(
+            "stack underflow (-4) in dup at line 4, column 1 (outer -> middle -> square -> dup)",
+            err.to_string()
+        );
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-10, -10];
+        intr.eval(&mut forth, ": div0 1 0 / ;").unwrap();
+        intr.eval(&mut forth, ": mod0 1 0 mod ;").unwrap();
+        intr.eval(&mut forth, "' div0 catch ' mod0 catch").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_wrapping_arithmetic() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![i32::MIN, i32::MAX, -2, i32::MIN, i32::MIN, i32::MIN];
+        intr.eval(&mut forth, "2147483647 1 +").unwrap();
+        intr.eval(&mut forth, "-2147483648 1 -").unwrap();
+        intr.eval(&mut forth, "2147483647 2 *").unwrap();
+        intr.eval(&mut forth, "-2147483648 -1 /").unwrap();
+        intr.eval(&mut forth, "-2147483648 negate").unwrap();
+        intr.eval(&mut forth, "-2147483648 abs").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![2147483647];
+        forth.set_checked_arithmetic(true);
+        let err = intr.eval(&mut forth, "2147483647 1 +").unwrap_err();
+        assert_eq!(error::ErrorKind::ResultOutOfRange, err.kind);
+        assert_eq!(-11, err.code());
+        assert!(intr.eval(&mut forth, "-2147483648 -1 /").is_err());
+        intr.eval(&mut forth, "clearstack 2147483646 1 +").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_variable() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0, 42, 50];
+        intr.eval(&mut forth, "variable counter counter @").unwrap();
+        intr.eval(&mut forth, "42 counter ! counter @").unwrap();
+        intr.eval(&mut forth, ": bump counter +! ;").unwrap();
+        intr.eval(&mut forth, "8 bump counter @").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_allot_table() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![20, 30, 7, 4, 65];
+        intr.eval(&mut forth, "variable table 2 cells allot").unwrap();
+        intr.eval(&mut forth, "10 table ! 20 table cell+ ! 30 table 2 cells + !").unwrap();
+        intr.eval(&mut forth, "table cell+ @ table 2 cells + @").unwrap();
+        intr.eval(&mut forth, "here 7 , dup @ swap here swap -").unwrap();
+        intr.eval(&mut forth, "here 65 c, c@ align").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        assert_eq!(0, forth.here() % forth::CELL_SIZE);
+    }
+
+    #[test]
+    fn test_invalid_address() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let err = intr.eval(&mut forth, "here @").unwrap_err();
+        assert_eq!(error::ErrorKind::InvalidAddress, err.kind);
+        assert_eq!(-9, err.code());
+        assert!(intr.eval(&mut forth, "1 -4 !").is_err());
+        let err = intr.eval(&mut forth, "100000 allot").unwrap_err();
+        assert_eq!(error::ErrorKind::DictionaryOverflow, err.kind);
+    }
+
+    #[test]
+    fn test_create() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![2, 3];
+        intr.eval(&mut forth, "create primes 2 , 3 , 5 ,").unwrap();
+        intr.eval(&mut forth, "primes @ primes cell+ @").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_create_does() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![30, 10, 99];
+        intr.eval(&mut forth, ": array create cells allot does> swap cells + ;").unwrap();
+        intr.eval(&mut forth, "3 array scores").unwrap();
+        intr.eval(&mut forth, "10 0 scores ! 30 2 scores !").unwrap();
+        intr.eval(&mut forth, "2 scores @ 0 scores @").unwrap();
+        intr.eval(&mut forth, ": const create , does> @ ;").unwrap();
+        intr.eval(&mut forth, "99 const answer answer").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_defining_word_parses_input() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![7, 0];
+        intr.eval(&mut forth, ": make-const constant ;").unwrap();
+        intr.eval(&mut forth, ": make-var variable ;").unwrap();
+        intr.eval(&mut forth, "7 make-const seven make-var total").unwrap();
+        intr.eval(&mut forth, "seven total @").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        let err = intr.eval(&mut forth, ": bad does> ; bad").unwrap_err();
+        assert_eq!(-31, err.code());
+    }
+
+    #[test]
+    fn test_compiled_definition() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        intr.eval(&mut forth, ": sq dup * ;").unwrap();
+        intr.eval(&mut forth, ": sq+2 sq 2 + ;").unwrap();
+        match forth.get_word("sq+2").unwrap().1 {
+            forth::WordKind::Colon(body) => {
+                assert_eq!(forth::Instruction::Call(forth.find_word("sq").unwrap()), body[0]);
+                assert_eq!(forth::Instruction::Literal(2), body[1]);
+                assert!(matches!(body[2], forth::Instruction::Builtin(_)));
+            }
+            _ => panic!("sq+2 is not a colon definition"),
+        }
+        intr.eval(&mut forth, "3 sq+2").unwrap();
+        assert_eq!(vec![11], forth.get_stack());
+    }
+
+    #[test]
+    fn test_redefinition_keeps_old_binding() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![25, 126];
+        intr.eval(&mut forth, ": square dup * ;").unwrap();
+        intr.eval(&mut forth, ": area square ;").unwrap();
+        intr.eval(&mut forth, ": square dup dup * * ;").unwrap();
+        intr.eval(&mut forth, ": square square 1 + ;").unwrap();
+        intr.eval(&mut forth, "5 area 5 square").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_redefinition_keeps_interpreter_words() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        intr.eval(&mut forth, ": mk create ; : create 99 ; mk foo").unwrap();
+        assert!(forth.get_stack().is_empty());
+        assert!(matches!(forth.get_word("foo"), Some((_, forth::WordKind::Created { .. }))));
+        intr.eval(&mut forth, "create").unwrap();
+        assert_eq!(vec![99], forth.get_stack());
+    }
+
+    #[test]
+    fn test_undefined_word_in_definition() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let err = intr.eval(&mut forth, ": broken 1 missing + ;").unwrap_err();
+        assert_eq!(error::ErrorKind::UndefinedWord, err.kind);
+        assert_eq!(Some("missing".to_string()), err.word);
+        assert_eq!(Some(error::Position { line: 1, column: 12 }), err.position);
+        assert!(forth.find_word("broken").is_none());
+    }
+
+    #[test]
+    fn test_recurse() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![120, 4000];
+        intr.eval(&mut forth, ": fact dup 1 > if dup 1 - recurse * then ;").unwrap();
+        intr.eval(&mut forth, ": deep dup 0 > if 1 - recurse 1 + then ;").unwrap();
+        intr.eval(&mut forth, "5 fact 4000 deep").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        let err = intr.eval(&mut forth, "recurse").unwrap_err();
+        assert_eq!(error::ErrorKind::CompileOnly, err.kind);
+    }
+
+    #[test]
+    fn test_return_stack_overflow() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![-5];
+        intr.eval(&mut forth, ": forever recurse ;").unwrap();
+        let err = intr.eval(&mut forth, "forever").unwrap_err();
+        assert_eq!(error::ErrorKind::ReturnStackOverflow, err.kind);
+        assert_eq!(forth::DEFAULT_MAX_CALL_DEPTH, err.backtrace.len());
+        assert_eq!(
+            "return stack overflow (-5) in forever at line 2, column 1 \
+             (forever -> forever -> forever -> forever -> ... -> forever -> forever -> forever -> forever)",
+            err.to_string()
+        );
+        forth.set_max_call_depth(10);
+        intr.eval(&mut forth, ": nest dup if 1 - nest then ;").unwrap_err();
+        intr.eval(&mut forth, ": nest dup if 1 - recurse then ;").unwrap();
+        intr.eval(&mut forth, "9 nest drop ' forever catch").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        intr.eval(&mut forth, "10 nest").unwrap_err();
+    }
+
+    #[test]
+    fn test_multi_line_definition() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![0, 0, 6];
+        intr.eval(&mut forth, "state @").unwrap();
+        intr.eval(&mut forth, ": sum-to").unwrap();
+        assert!(forth.state());
+        intr.eval(&mut forth, "  0 swap 1 + 1 do").unwrap();
+        intr.eval(&mut forth, "    i +").unwrap();
+        intr.eval(&mut forth, "  loop ;").unwrap();
+        assert!(!forth.state());
+        intr.eval(&mut forth, ": flag state @ ; flag 3 sum-to").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_unfinished_definition() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        intr.eval(&mut forth, ": broken 1 +").unwrap();
+        let err = intr.eval(&mut forth, "nothing-here ;").unwrap_err();
+        assert_eq!(error::ErrorKind::UndefinedWord, err.kind);
+        assert_eq!(Some(error::Position { line: 2, column: 1 }), err.position);
+        assert!(!forth.state());
+        assert_eq!(None, forth.find_word("broken"));
+        intr.eval(&mut forth, ": open 1 if").unwrap();
+        let err = intr.finish(&mut forth).unwrap_err();
+        assert_eq!(error::ErrorKind::UnexpectedEnd, err.kind);
+        assert_eq!(Some("open".to_string()), err.word);
+        intr.eval(&mut forth, "1 open").unwrap_err();
+    }
+
+    #[test]
+    fn test_whitespace_and_comments() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![3, 12];
+        intr.eval(&mut forth, "1\t  2 +\r").unwrap();
+        intr.eval(&mut forth, ": triple ( n -- 3n ) 3 *  \\ ( not closed").unwrap();
+        intr.eval(&mut forth, "\t;  4 triple ( done )").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        let err = intr.eval(&mut forth, "1 2 +\n\n  3 undefined-word").unwrap_err();
+        assert_eq!(Some(error::Position { line: 6, column: 5 }), err.position);
+        let err = intr.eval(&mut forth, &"1 ".repeat(forth::TIB_SIZE)).unwrap_err();
+        assert_eq!(error::ErrorKind::LineTooLong, err.kind);
+        forth.clear();
+        intr.eval(&mut forth, &"1 ".repeat(forth::TIB_SIZE / 2)).unwrap();
+        assert_eq!(forth::TIB_SIZE / 2, forth.depth());
+        forth.clear();
+        intr.eval(&mut forth, &"1 \n".repeat(forth::TIB_SIZE)).unwrap();
+        assert_eq!(forth::TIB_SIZE, forth.depth());
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![16, 16, 2, 3, 120, 0, 0];
+        intr.eval(&mut forth, "source nip >in @").unwrap();
+        intr.eval(&mut forth, "parse-name   hi nip").unwrap();
+        intr.eval(&mut forth, "41 parse abc) nip").unwrap();
+        intr.eval(&mut forth, "parse-name xyz drop c@").unwrap();
+        intr.eval(&mut forth, ": rest 41 parse ; rest").unwrap();
+        intr.eval(&mut forth, ": name-len parse-name nip ; nip name-len").unwrap();
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![49, 10, 10, 1];
+        intr.eval(&mut forth, ": Square DUP * ;").unwrap();
+        intr.eval(&mut forth, "10 Constant TEN").unwrap();
+        intr.eval(&mut forth, ": Check ten IF 1 ELSE 0 THEN ;").unwrap();
+        intr.eval(&mut forth, "7 SQUARE Ten TEN CHECK").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        assert_eq!(vec!["Square", "TEN", "Check"], forth.get_words().iter().map(|w| w.0.as_str()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_case_sensitive() {
+        let mut forth = forth::Forth::empty();
+        let mut intr = interpreter::Interpreter::new();
+        intr.set_case_sensitive(true);
+        let vec = vec![1, 2];
+        intr.eval(&mut forth, "1 constant one 2 constant ONE one ONE").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        let err = intr.eval(&mut forth, "1 DUP").unwrap_err();
+        assert_eq!(error::ErrorKind::UndefinedWord, err.kind);
+        assert_eq!(Some("DUP".to_string()), err.word);
+    }
+
+    #[test]
+    fn test_number_bases() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![255, 10, 10, -1, 65, 32, 8, 5, -16];
+        intr.eval(&mut forth, "$ff #10 %1010 $FFFFFFFF 'A'").unwrap();
+        intr.eval(&mut forth, "hex 20 decimal octal 10 binary 101 hex -10 decimal").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        assert_eq!(10, forth.base());
+        let err = intr.eval(&mut forth, "hex 1G").unwrap_err();
+        assert_eq!(error::ErrorKind::InvalidNumber(16), err.kind);
+        assert_eq!("invalid number in base 16 (-24) in 1G at line 3, column 5", err.to_string());
+        let err = intr.eval(&mut forth, "decimal %102").unwrap_err();
+        assert_eq!(error::ErrorKind::InvalidNumber(2), err.kind);
+        let err = intr.eval(&mut forth, "face").unwrap_err();
+        assert_eq!(error::ErrorKind::UndefinedWord, err.kind);
+        let err = intr.eval(&mut forth, "clearstack 16 base ! face : fade ;").unwrap_err();
+        assert_eq!(error::ErrorKind::InvalidName, err.kind);
+        assert_eq!(vec![0xface], forth.get_stack());
+        assert_eq!("-FF", forth.format_number(-255));
+    }
+
+    #[test]
+    fn test_invalid_base_reads_decimal() {
+        let (mut forth, output, _) = forth::Forth::buffered();
+        let intr = interpreter::Interpreter::new();
+        intr.eval(&mut forth, "100 base ! 10 .").unwrap();
+        assert_eq!("10 ", output.take());
+        intr.eval(&mut forth, "-1 base ! 10 1 base ! 12").unwrap();
+        assert_eq!(vec![10, 12], forth.get_stack());
+    }
+
+    #[test]
+    fn test_number_output() {
+        let (mut forth, output, _) = forth::Forth::buffered();
+        let intr = interpreter::Interpreter::new();
+        let vec = vec![1, 2, 2, 3];
+        intr.eval(&mut forth, "1 2 3 . .s depth").unwrap();
+        assert_eq!("3 <2> 1 2 ", output.take());
+        intr.eval(&mut forth, "variable v 42 v ! v ? -1 u. -5 4 .r 7 2 u.r .s 3").unwrap();
+        assert_eq!("42 4294967295   -5 7<3> 1 2 2 ", output.take());
+        assert_eq!(vec, forth.get_stack());
+        assert_eq!("4294967295", forth.format_unsigned(u32::MAX));
+        intr.eval(&mut forth, "1 70000 .r 1 70000 u.r").unwrap();
+        assert_eq!(format!("{}1{}1", " ".repeat(69999), " ".repeat(69999)), output.take());
+        let err = intr.eval(&mut forth, "clearstack .").unwrap_err();
+        assert_eq!(error::ErrorKind::StackUnderflow, err.kind);
+        assert_eq!(Some(".".to_string()), err.word);
+    }
+
+    #[test]
+    fn test_legacy_dot() {
+        let (mut forth, output, _) = forth::Forth::buffered();
+        let mut intr = interpreter::Interpreter::new();
+        intr.set_legacy_dot(true);
+        let vec = vec![1, 2];
+        intr.eval(&mut forth, ": show . ;").unwrap();
+        intr.eval(&mut forth, "1 2 . show").unwrap();
+        assert_eq!("> [1, 2]\n> [1, 2]\n", output.contents());
+        assert_eq!(vec, forth.get_stack());
+    }
+
+    #[test]
+    fn test_pictured_output() {
+        let mut forth = forth::Forth::empty();
+        let intr = interpreter::Interpreter::new();
+        let held = |forth: &mut forth::Forth| {
+            let len = forth.pop().unwrap();
+            let addr = forth.pop().unwrap();
+            forth.string_at(addr, len).unwrap()
+        };
+        intr.eval(&mut forth, "5 0 <# # # # #>").unwrap();
+        assert_eq!("005", held(&mut forth));
+        intr.eval(&mut forth, ": cents <# # # 46 hold #s #> ; 12345 0 cents").unwrap();
+        assert_eq!("123.45", held(&mut forth));
+        intr.eval(&mut forth, "-42 dup abs 0 <# #s rot sign #>").unwrap();
+        assert_eq!("-42", held(&mut forth));
+        intr.eval(&mut forth, "hex -1 -1 <# #s #> decimal").unwrap();
+        assert_eq!("FFFFFFFFFFFFFFFF", held(&mut forth));
+        intr.eval(&mut forth, "1234567 0 <# # # # 44 hold # # # 44 hold #s #>").unwrap();
+        assert_eq!("1,234,567", held(&mut forth));
+        intr.eval(&mut forth, "100 0 <# parse-name EUR holds 32 hold #s #>").unwrap();
+        assert_eq!("100 EUR", held(&mut forth));
+        intr.eval(&mut forth, "0 0 <# #s #> type").unwrap();
+        assert_eq!(Vec::<i32>::new(), forth.get_stack());
+        let err = intr.eval(&mut forth, ": fill 200 0 do 48 hold loop ; <# fill").unwrap_err();
+        assert_eq!(error::ErrorKind::HoldOverflow, err.kind);
+    }
+
+    #[test]
+    fn test_output_sink() {
+        let (mut forth, output, errors) = forth::Forth::buffered();
+        let intr = interpreter::Interpreter::new();
+        intr.eval(&mut forth, ".\" hello world\" 1 2 + . cr").unwrap();
+        intr.eval(&mut forth, ": greet .\" hi\" ; greet").unwrap();
+        assert_eq!("hello world\n3 \nhi\n", output.take());
+        intr.eval(&mut forth, "parse-name abc type").unwrap();
+        assert_eq!("abc", output.contents());
+        forth.write_error("oops\n").unwrap();
+        assert_eq!("oops\n", errors.contents());
+        assert_eq!("abc", output.contents());
+    }
+
+    #[test]
+    fn test_word_sets() {
+        let mut forth = Forth::empty();
+        let intr = Interpreter::builder()
+            .bare()
+            .with(WordSet::Arithmetic)
+            .with(WordSet::Stack)
+            .case_sensitive(true)
+            .build();
+        let vec = vec![4];
+        intr.eval(&mut forth, "2 dup +").unwrap();
+        assert_eq!(vec, forth.get_stack());
+        let err = intr.eval(&mut forth, "2 =").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+        let err = intr.eval(&mut forth, "DUP").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+        let intr = Interpreter::builder().without(WordSet::Output).build();
+        let err = intr.eval(&mut forth, "1 .").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+        intr.eval(&mut forth, ": square dup * ; 3 square").unwrap();
+        assert_eq!(vec![4, 2, 1, 9], forth.get_stack());
+    }
+
+    #[test]
+    fn test_register() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+        let mut forth = Forth::empty();
+        let mut intr = Interpreter::new();
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+        intr.register("TICK", move |_| {
+            counter.set(counter.get() + 1);
+            Ok(())
+        });
+        let mut total = 0;
+        intr.register_mut("tally", move |forth| {
+            total += forth.pop()?;
+            forth.push(total);
+            Ok(())
+        })
+        .stack_effect("( n -- total )");
+        intr.eval(&mut forth, "tick tick 3 tally 4 tally").unwrap();
+        assert_eq!(2, calls.get());
+        assert_eq!(vec![3, 7], forth.get_stack());
+        assert_eq!(Some("( n -- total )"), intr.builtin("TALLY").and_then(|b| b.effect()));
+        assert_eq!("TICK", intr.builtin("tick").unwrap().name());
+    }
+
+    #[test]
+    fn test_register_stack_effect() {
+        let mut forth = Forth::empty();
+        let mut intr = Interpreter::new();
+        intr.register("pair", |forth| {
+            let b = forth.pop()?;
+            let a = forth.pop()?;
+            forth.push(a + b);
+            Ok(())
+        })
+        .stack_effect("( a b -- a+b )");
+        intr.eval(&mut forth, "5").unwrap();
+        let err = intr.eval(&mut forth, "pair").unwrap_err();
+        assert_eq!(ErrorKind::StackUnderflow, err.kind);
+        assert_eq!(Some("pair".to_string()), err.word);
+        // Nothing was popped before the check
+        assert_eq!(vec![5], forth.get_stack());
+        intr.register("odd", |forth| {
+            let n = forth.pop()?;
+            forth.push(n & 1);
+            Ok(())
+        })
+        .stack_effect("( n )");
+        intr.eval(&mut forth, "odd").unwrap();
+        assert_eq!(vec![1], forth.get_stack());
+        assert!(intr.eval(&mut forth, "clearstack odd").is_err());
+    }
+
+    #[test]
+    fn test_unregister_and_override() {
+        let mut forth = Forth::empty();
+        let mut intr = Interpreter::new();
+        intr.eval(&mut forth, ": double 2 * ;").unwrap();
+        intr.register("*", |forth| {
+            let b = forth.pop()?;
+            let a = forth.pop()?;
+            forth.push(a * b + 1);
+            Ok(())
+        });
+        intr.eval(&mut forth, "3 double 3 2 *").unwrap();
+        assert_eq!(vec![6, 7], forth.get_stack());
+        assert!(intr.unregister("DUP"));
+        assert!(!intr.unregister("dup"));
+        assert!(intr.builtin("dup").is_none());
+        let err = intr.eval(&mut forth, "dup").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+    }
+
+    #[test]
+    fn test_execution_tokens() {
+        let mut forth = Forth::empty();
+        let intr = Interpreter::new();
+        intr.eval(&mut forth, "3 4 ' + execute ' dup execute").unwrap();
+        assert_eq!(vec![7, 7], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : square dup * ; 5 ' square execute").unwrap();
+        assert_eq!(vec![25], forth.get_stack());
+        intr.eval(&mut forth, "clearstack 10 constant ten ' ten execute").unwrap();
+        assert_eq!(vec![10], forth.get_stack());
+        // A dispatch table of builtins and colon words
+        intr.eval(&mut forth, "clearstack create ops ' + , ' * , ' square ,").unwrap();
+        intr.eval(&mut forth, ": op ( a b n -- c ) cells ops + @ execute ;").unwrap();
+        intr.eval(&mut forth, "2 3 0 op 2 3 1 op 4 2 op").unwrap();
+        assert_eq!(vec![5, 6, 16], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : apply ['] square execute ; 6 apply").unwrap();
+        assert_eq!(vec![36], forth.get_stack());
+        let err = intr.eval(&mut forth, "' nothing").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+        assert_eq!(Some("nothing".to_string()), err.word);
+        let err = intr.eval(&mut forth, "12345 execute").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+        let err = intr.eval(&mut forth, "['] dup").unwrap_err();
+        assert_eq!(ErrorKind::CompileOnly, err.kind);
+        intr.eval(&mut forth, "clearstack 1 0 ' / catch").unwrap();
+        assert_eq!(vec![0, 0, -10], forth.get_stack());
+    }
+
+    #[test]
+    fn test_interpreter_word_tokens() {
+        let mut forth = Forth::empty();
+        let mut intr = Interpreter::new();
+        intr.eval(&mut forth, "3 ' dup ' execute catch").unwrap();
+        assert_eq!(vec![3, 3, 0], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : run-it execute ; 4 ' dup run-it").unwrap();
+        assert_eq!(vec![4, 4], forth.get_stack());
+        intr.eval(&mut forth, "clearstack 1 0 ' / ' catch execute").unwrap();
+        assert_eq!(vec![0, 0, -10], forth.get_stack());
+        intr.eval(&mut forth, "clearstack 5 ' constant execute five 6 ' value execute six five six").unwrap();
+        assert_eq!(vec![5, 6], forth.get_stack());
+        intr.eval(&mut forth, "clearstack ' variable execute v ' create execute c ' defer execute d").unwrap();
+        assert!(["v", "c", "d"].iter().all(|name| forth.find_word(name).is_some()));
+        assert!(intr.builtin("constant").is_some());
+        assert!(intr.unregister("constant"));
+        let err = intr.eval(&mut forth, "' constant").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+    }
+
+    #[test]
+    fn test_find_and_body() {
+        let mut forth = Forth::empty();
+        let intr = Interpreter::new();
+        intr.eval(&mut forth, "create name 3 c, 'd' c, 'u' c, 'p' c,").unwrap();
+        intr.eval(&mut forth, "7 name find drop execute").unwrap();
+        assert_eq!(vec![7, 7], forth.get_stack());
+        intr.eval(&mut forth, "clearstack create nope 2 c, 'q' c, 'q' c,").unwrap();
+        intr.eval(&mut forth, "nope find swap nope =").unwrap();
+        assert_eq!(vec![0, -1], forth.get_stack());
+        intr.eval(&mut forth, "clearstack create data 42 , ' data >body @ ' data >body data =").unwrap();
+        assert_eq!(vec![42, -1], forth.get_stack());
+        let err = intr.eval(&mut forth, "' dup >body").unwrap_err();
+        assert_eq!(ErrorKind::NotCreated, err.kind);
+        let err = intr.eval(&mut forth, "' dup compile,").unwrap_err();
+        assert_eq!(ErrorKind::CompileOnly, err.kind);
+    }
+
+    #[test]
+    fn test_immediate() {
+        let (mut forth, output, _) = Forth::buffered();
+        let intr = Interpreter::new();
+        intr.eval(&mut forth, ": hi 72 73 ; immediate").unwrap();
+        intr.eval(&mut forth, ": greet hi ;").unwrap();
+        assert_eq!(vec![72, 73], forth.get_stack());
+        intr.eval(&mut forth, "clearstack greet").unwrap();
+        assert!(forth.get_stack().is_empty());
+        intr.eval(&mut forth, ": five [ 2 3 + ] literal ; five").unwrap();
+        assert_eq!(vec![5], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : st [ state @ ] literal state @ ; st").unwrap();
+        assert_eq!(vec![0, 0], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : a [char] abc [ 'b' ] literal ; a").unwrap();
+        assert_eq!(vec![97, 98], forth.get_stack());
+        intr.eval(&mut forth, "clearstack create name 2 c, 'h' c, 'i' c, name find nip").unwrap();
+        assert_eq!(vec![1], forth.get_stack());
+        intr.eval(&mut forth, ": shout .\" compiling\" ; immediate : quiet shout ;").unwrap();
+        assert_eq!("compiling\n", output.take());
+        let err = intr.eval(&mut forth, "1 literal").unwrap_err();
+        assert_eq!(ErrorKind::CompileOnly, err.kind);
+        let err = intr.eval(&mut forth, "]").unwrap_err();
+        assert_eq!(ErrorKind::ControlMismatch, err.kind);
+    }
+
+    #[test]
+    fn test_postpone() {
+        let mut forth = Forth::empty();
+        let intr = Interpreter::new();
+        intr.eval(&mut forth, ": unless 0 postpone literal postpone = postpone if ; immediate").unwrap();
+        intr.eval(&mut forth, ": check unless 7 then ; 0 check 1 check").unwrap();
+        assert_eq!(vec![7], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : twice, postpone dup postpone + ; immediate").unwrap();
+        intr.eval(&mut forth, ": double twice, ; 4 double").unwrap();
+        assert_eq!(vec![8], forth.get_stack());
+        // Postponing an immediate word compiles a call to it
+        intr.eval(&mut forth, "clearstack : ten 10 postpone literal ; immediate").unwrap();
+        intr.eval(&mut forth, ": also-ten postpone ten ; immediate : tens ten also-ten ; tens").unwrap();
+        assert_eq!(vec![10, 10], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : end postpone ; ; immediate : one 1 end one").unwrap();
+        assert_eq!(vec![1], forth.get_stack());
+        let err = intr.eval(&mut forth, ": bad postpone nothing ;").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+        assert!(!forth.state());
+    }
+
+    #[test]
+    fn test_catch_keeps_return_stack() {
+        let mut forth = Forth::empty();
+        let intr = Interpreter::new();
+        intr.eval(&mut forth, ": t 5 >r ['] 2r> catch r> ; t").unwrap();
+        assert_eq!(vec![-6, 5], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : u 7 >r ['] 2r@ catch ['] unloop catch r> ; u").unwrap();
+        assert_eq!(vec![-6, -6, 7], forth.get_stack());
+        assert_eq!(0, forth.return_depth());
+    }
+
+    #[test]
+    fn test_catch_deferred() {
+        let mut forth = Forth::empty();
+        let intr = Interpreter::new();
+        intr.eval(&mut forth, "defer d : u ['] d catch ; u").unwrap();
+        assert_eq!(vec![-13], forth.get_stack());
+        intr.eval(&mut forth, "clearstack defer d2 ' + is d2 1 ' d2 catch").unwrap();
+        assert_eq!(vec![0, -4], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : sq dup * ; ' sq is d 3 ' d catch").unwrap();
+        assert_eq!(vec![9, 0], forth.get_stack());
+        forth.set_max_call_depth(0);
+        intr.eval(&mut forth, "clearstack ' sq catch").unwrap();
+        assert_eq!(vec![-5], forth.get_stack());
+    }
+
+    #[test]
+    fn test_value_and_to() {
+        let mut forth = Forth::empty();
+        let intr = Interpreter::new();
+        intr.eval(&mut forth, "10 value limit limit 20 to limit limit").unwrap();
+        assert_eq!(vec![10, 20], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : raise ( n -- ) limit + to limit ; 5 raise limit").unwrap();
+        assert_eq!(vec![25], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : show limit ; 1 to limit show").unwrap();
+        assert_eq!(vec![1], forth.get_stack());
+        let err = intr.eval(&mut forth, "3 constant three 4 to three").unwrap_err();
+        assert_eq!(ErrorKind::InvalidName, err.kind);
+        let err = intr.eval(&mut forth, ": bad 4 to three ;").unwrap_err();
+        assert_eq!(ErrorKind::InvalidName, err.kind);
+        assert!(!forth.state());
+        let err = intr.eval(&mut forth, "4 to nothing").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+    }
+
+    #[test]
+    fn test_defer_and_is() {
+        let mut forth = Forth::empty();
+        let intr = Interpreter::new();
+        intr.eval(&mut forth, "defer logger : log-it 5 logger ;").unwrap();
+        let err = intr.eval(&mut forth, "log-it").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+        assert_eq!(Some("logger".to_string()), err.word);
+        intr.eval(&mut forth, "clearstack : twice 2 * ; ' twice is logger log-it").unwrap();
+        assert_eq!(vec![10], forth.get_stack());
+        intr.eval(&mut forth, "clearstack ' negate is logger log-it").unwrap();
+        assert_eq!(vec![-5], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : use-dup ['] dup is logger ; use-dup log-it").unwrap();
+        assert_eq!(vec![5, 5], forth.get_stack());
+        intr.eval(&mut forth, "clearstack action-of logger ' dup =").unwrap();
+        assert_eq!(vec![-1], forth.get_stack());
+        intr.eval(&mut forth, "clearstack : current action-of logger ; current ' dup =").unwrap();
+        assert_eq!(vec![-1], forth.get_stack());
+        intr.eval(&mut forth, "clearstack 3 ' logger catch").unwrap();
+        assert_eq!(vec![3, 3, 0], forth.get_stack());
+        let err = intr.eval(&mut forth, "' dup is twice").unwrap_err();
+        assert_eq!(ErrorKind::InvalidName, err.kind);
+        let err = intr.eval(&mut forth, "defer loop-back ' loop-back is loop-back").unwrap_err();
+        assert_eq!(ErrorKind::DeferLoop, err.kind);
+        let err = intr.eval(&mut forth, "defer a defer b ' a is b ' b is a").unwrap_err();
+        assert_eq!(ErrorKind::DeferLoop, err.kind);
+        assert_eq!(Some("a".to_string()), err.word);
+        let err = intr.eval(&mut forth, "defer unset action-of unset").unwrap_err();
+        assert_eq!(ErrorKind::UndefinedWord, err.kind);
+    }
+
+    #[test]
+    fn test_allot_keeps_system_area() {
+        let mut forth = Forth::empty();
+        let intr = Interpreter::new();
+        let err = intr.eval(&mut forth, "here negate allot").unwrap_err();
+        assert_eq!(ErrorKind::InvalidAddress, err.kind);
+        intr.eval(&mut forth, "1 2 base @").unwrap();
+        assert_eq!(vec![1, 2, 10], forth.get_stack());
+    }
+
+    #[test]
+    fn test_raw_bytes() {
+        let (mut forth, output, _) = Forth::buffered();
+        let intr = Interpreter::new();
+        intr.eval(&mut forth, "create raw 200 c, 201 c, raw 2 type").unwrap();
+        assert_eq!(vec![200, 201], output.bytes());
+        intr.eval(&mut forth, "0 0 <# raw 2 holds #> over c@ swap").unwrap();
+        assert_eq!(vec![200, 2], forth.get_stack()[1..]);
+    }
+}
@@ -1,375 +1,116 @@
-mod forth;
-mod interpreter;
-mod operators;
-mod operators_binary;
-
 use std::io::{self, BufRead};
 
-fn exit_ruforth(cmd: &str) -> bool {
-    match cmd {
-        "exit" => true,
-        _ => false,
-    }
-}
-
-fn start_ruforth() {
-
-    let mut forth = forth::Forth::empty();
-    let intr = interpreter::Interpreter::new();
-    let stdin = io::stdin();
-    for line in stdin.lock().lines() {
-        let input_user = line.unwrap().trim().to_string();
-
-        if exit_ruforth(&input_user) {
-            println!("Bye!");
-            return;
-        } else {
-            intr.eval(&mut forth, &input_user);
-            println!("ok");
-        }
-    }
-}
-
-fn main() {
-    start_ruforth();
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_add() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(5);
-        intr.eval(&mut forth, "2 3 +");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_sub() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(-1);
-        intr.eval(&mut forth, "2 3 -");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_mul() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(6);
-        intr.eval(&mut forth, "2 3 *");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_div() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(2);
-        intr.eval(&mut forth, "6 3 /");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_mod() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(0);
-        intr.eval(&mut forth, "3 3 mod");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_dup() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(3);
-        vec.push(3);
-        intr.eval(&mut forth, "3 dup");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_swap() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(2);
-        vec.push(3);
-        intr.eval(&mut forth, "3 2 swap");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_negate() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(-99);
-        intr.eval(&mut forth, "99 negate");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_abs_negative() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(99);
-        intr.eval(&mut forth, "-99 abs");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_abs_positive() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(99);
-        intr.eval(&mut forth, "99 abs");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_rot() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(4);
-        vec.push(5);
-        vec.push(6);
-        intr.eval(&mut forth, "6 4 5 rot");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_drop() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(2);
-        intr.eval(&mut forth, "2 3 drop");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_nip() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(1);
-        vec.push(3);
-        intr.eval(&mut forth, "1 2 3 nip");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_tuck() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(1);
-        vec.push(2);
-        vec.push(4);
-        vec.push(3);
-        vec.push(4);
-        intr.eval(&mut forth, "1 2 3 4 tuck");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_over() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(1);
-        vec.push(2);
-        vec.push(3);
-        vec.push(4);
-        vec.push(3);
-        intr.eval(&mut forth, "1 2 3 4 over");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_max() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(3);
-        intr.eval(&mut forth, "1 3 max");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_min() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(1);
-        intr.eval(&mut forth, "1 3 min");
-        assert_eq!(vec, forth.get_stack());
-    }
+use ruforth::{Forth, Interpreter};
 
-    #[test]
-    fn test_clearstack() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let vec : Vec<i32> = Vec::new();
-        intr.eval(&mut forth, "1 3 4 + clearstack");
-        assert_eq!(vec, forth.get_stack());
-    }
+#[cfg(unix)]
+mod sigint {
+    use std::os::raw::c_int;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, OnceLock};
 
-    #[test]
-    fn test_add_word() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(25);
-        intr.eval(&mut forth, ": square dup * ;");
-        intr.eval(&mut forth, "5 square");
-        assert_eq!(vec, forth.get_stack());
-    }
-
-    #[test]
-    fn test_add_constant() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(100);
-        intr.eval(&mut forth, "100 constant HUNDRED");
-        intr.eval(&mut forth, "HUNDRED");
-        assert_eq!(vec, forth.get_stack());
-    }
+    const SIGINT: c_int = 2;
 
-    #[test]
-    fn test_use_constant() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(125);
-        intr.eval(&mut forth, "100 constant HUNDRED");
-        intr.eval(&mut forth, "HUNDRED 25 +");
-        assert_eq!(vec, forth.get_stack());
-    }
+    static INTERRUPT: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    static RUNNING: AtomicBool = AtomicBool::new(false);
 
-    #[test]
-    fn test_multiple_commands() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(14);
-        intr.eval(&mut forth, "2 3 4 * +");
-        assert_eq!(vec, forth.get_stack());
+    // The standard library has no way to catch signals, and the crate has
+    // no dependencies, so the two libc calls needed are declared here
+    extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+        fn _exit(status: c_int) -> !;
     }
 
-    #[test]
-    fn test_few_stack() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let vec : Vec<i32> = Vec::new();
-        intr.eval(&mut forth, "2 +");
-        assert_eq!(vec, forth.get_stack());
+    extern "C" fn on_sigint(_: c_int) {
+        if !RUNNING.load(Ordering::SeqCst) {
+            // Quit at the prompt, as the shell expects for Ctrl-C
+            unsafe { _exit(128 + SIGINT) }
+        }
+        if let Some(flag) = INTERRUPT.get() {
+            flag.store(true, Ordering::SeqCst);
+        }
     }
 
-    #[test]
-    fn test_equals_1() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(-1);
-        intr.eval(&mut forth, "2 2 =");
-        assert_eq!(vec, forth.get_stack());
+    /// Whether input is being interpreted, in which case Ctrl-C only
+    /// stops it.
+    pub fn set_running(running: bool) {
+        RUNNING.store(running, Ordering::SeqCst);
     }
 
-    #[test]
-    fn test_equals_2() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(0);
-        intr.eval(&mut forth, "2 3 =");
-        assert_eq!(vec, forth.get_stack());
+    /// Make Ctrl-C stop the running word instead of killing the REPL, which
+    /// it still quits when nothing runs.
+    pub fn install(flag: Arc<AtomicBool>) {
+        if INTERRUPT.set(flag).is_ok() {
+            unsafe {
+                signal(SIGINT, on_sigint);
+            }
+        }
     }
+}
 
-    #[test]
-    fn test_not_equals_1() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(-1);
-        intr.eval(&mut forth, "2 3 !=");
-        assert_eq!(vec, forth.get_stack());
-    }
+// `exit` on its own quits, unless it is a line of an open definition
+fn exit_ruforth(forth: &Forth, cmd: &str) -> bool {
+    cmd == "exit" && !forth.state() && !forth.in_definition()
+}
 
-    #[test]
-    fn test_not_equals_2() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(0);
-        intr.eval(&mut forth, "2 2 !=");
-        assert_eq!(vec, forth.get_stack());
-    }
+fn start_ruforth(mut forth: Forth, intr: Interpreter) {
+    #[cfg(unix)]
+    sigint::install(forth.interrupt_handle());
+    let stdin = io::stdin();
+    run_ruforth(&mut forth, &intr, stdin.lock());
+}
 
-    #[test]
-    fn test_greater() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(-1);
-        intr.eval(&mut forth, "4 2 >");
-        assert_eq!(vec, forth.get_stack());
-    }
+fn run_ruforth(forth: &mut Forth, intr: &Interpreter, input: impl BufRead) {
+    for line in input.lines() {
+        let input_user = line.unwrap().trim().to_string();
 
-    #[test]
-    fn test_less() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(-1);
-        intr.eval(&mut forth, "2 4 <");
-        assert_eq!(vec, forth.get_stack());
+        if exit_ruforth(forth, &input_user) {
+            let _ = forth.write_output("Bye!\n");
+            return;
+        }
+        #[cfg(unix)]
+        sigint::set_running(true);
+        let result = intr.eval(forth, &input_user);
+        #[cfg(unix)]
+        sigint::set_running(false);
+        let _ = match result {
+            // The definition goes on on the next line
+            Ok(()) if forth.state() => forth.write_output("compiled\n"),
+            Ok(()) => forth.write_output("ok\n"),
+            Err(e) => forth.write_error(&format!("Error: {}\n", e)),
+        };
+    }
+    if let Err(e) = intr.finish(forth) {
+        let _ = forth.write_error(&format!("Error: {}\n", e));
     }
+}
 
-    #[test]
-    fn test_greater_equals() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(-1);
-        intr.eval(&mut forth, "2 2 >=");
-        assert_eq!(vec, forth.get_stack());
-    }
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let flag = |name: &str| args.iter().any(|arg| arg == name);
+
+    let mut forth = Forth::empty();
+    forth.set_checked_arithmetic(flag("--checked"));
+    let max_depth = args.iter().find_map(|arg| arg.strip_prefix("--max-depth="));
+    if let Some(depth) = max_depth.and_then(|depth| depth.parse().ok()) {
+        forth.set_max_call_depth(depth);
+    }
+    let intr = Interpreter::builder()
+        .case_sensitive(flag("--case-sensitive"))
+        .legacy_dot(flag("--legacy-dot"))
+        .build();
+    start_ruforth(forth, intr);
+}
 
-    #[test]
-    fn test_less_equals() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(-1);
-        intr.eval(&mut forth, "4 4 <=");
-        assert_eq!(vec, forth.get_stack());
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
-    fn test_invert() {
-        let mut forth = forth::Forth::empty();
-        let intr = interpreter::Interpreter::new();
-        let mut vec = Vec::new();
-        vec.push(-1);
-        intr.eval(&mut forth, "0 invert");
-        assert_eq!(vec, forth.get_stack());
+    fn test_exit_inside_definition() {
+        let (mut forth, output, errors) = Forth::buffered();
+        let intr = Interpreter::new();
+        run_ruforth(&mut forth, &intr, ": foo 1\nexit\n2 ;\nfoo .\nexit\n1 .\n".as_bytes());
+        assert_eq!("compiled\ncompiled\nok\n1 ok\nBye!\n", output.contents());
+        assert_eq!("", errors.contents());
     }
-
-}
\ No newline at end of file
+}
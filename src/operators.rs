@@ -1,72 +1,83 @@
 use std::cmp;
+use crate::error::ErrorKind;
 use crate::forth::{Forth, ForthResult};
 
-type ArOperator = fn(i32, i32) -> i32;
-
-fn arithmetic_operator(name: &str, op: ArOperator, forth: &mut Forth) -> ForthResult<()> {
-    let x = forth.pop(format!(
-        "Empty stack: for first argument for {}",
-        name.to_string()
-    ))?;
-    let y = forth.pop(format!(
-        "Empty stack: for second argument for {}",
-        name.to_string()
-    ))?;
-    forth.push(op(x, y));
+// Operators return the wrapped result and whether it overflowed
+type ArOperator = fn(i32, i32) -> (i32, bool);
+
+// Two's-complement wrapping, unless the Forth asks for checked arithmetic
+fn push_result(forth: &mut Forth, (result, overflow): (i32, bool)) -> ForthResult<()> {
+    if overflow && forth.checked_arithmetic() {
+        return Err(ErrorKind::ResultOutOfRange.into());
+    }
+    forth.push(result);
     Ok(())
 }
 
+fn arithmetic_operator(op: ArOperator, forth: &mut Forth) -> ForthResult<()> {
+    let x = forth.pop()?;
+    let y = forth.pop()?;
+    push_result(forth, op(x, y))
+}
+
 pub fn add(forth: &mut Forth) -> ForthResult<()> {
-    arithmetic_operator("+", |x, y| x + y, forth)
+    arithmetic_operator(|x, y| x.overflowing_add(y), forth)
 }
 
 pub fn sub(forth: &mut Forth) -> ForthResult<()> {
-    arithmetic_operator("-", |x, y| y - x, forth)
+    arithmetic_operator(|x, y| y.overflowing_sub(x), forth)
 }
 
 pub fn mul(forth: &mut Forth) -> ForthResult<()> {
-    arithmetic_operator("*", |x, y| x * y, forth)
+    arithmetic_operator(|x, y| x.overflowing_mul(y), forth)
+}
+
+fn division_operator(op: ArOperator, forth: &mut Forth) -> ForthResult<()> {
+    let x = forth.pop()?;
+    let y = forth.pop()?;
+    if x == 0 {
+        return Err(ErrorKind::DivisionByZero.into());
+    }
+    push_result(forth, op(x, y))
 }
 
 pub fn div(forth: &mut Forth) -> ForthResult<()> {
-    arithmetic_operator("/", |x, y| y / x, forth)
+    division_operator(|x, y| y.overflowing_div(x), forth)
 }
 
 pub fn modulus(forth: &mut Forth) -> ForthResult<()> {
-    arithmetic_operator("mod", |x, y| y % x, forth)
+    division_operator(|x, y| y.overflowing_rem(x), forth)
 }
 
 pub fn negate(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for negate".to_string())?;
-    forth.push(-a);
-    Ok(())
+    let a = forth.pop()?;
+    push_result(forth, a.overflowing_neg())
 }
 
 pub fn abs(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for abs".to_string())?;
-    forth.push(a.abs());
-    Ok(())
+    let a = forth.pop()?;
+    push_result(forth, a.overflowing_abs())
 }
 
 pub fn dup(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for dup".to_string())?;
+    let a = forth.pop()?;
     forth.push(a);
     forth.push(a);
     Ok(())
 }
 
 pub fn swap(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for first element in swap".to_string())?;
-    let b = forth.pop("Empty stack for second element in swap".to_string())?;
+    let a = forth.pop()?;
+    let b = forth.pop()?;
     forth.push(a);
     forth.push(b);
     Ok(())
 }
 
 pub fn rot(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for first element in rot".to_string())?;
-    let b = forth.pop("Empty stack for second element in rot".to_string())?;
-    let c = forth.pop("Empty stack for third element in rot".to_string())?;
+    let a = forth.pop()?;
+    let b = forth.pop()?;
+    let c = forth.pop()?;
     forth.push(b);
     forth.push(a);
     forth.push(c);
@@ -74,20 +85,20 @@ pub fn rot(forth: &mut Forth) -> ForthResult<()> {
 }
 
 pub fn drop(forth: &mut Forth) -> ForthResult<()> {
-    forth.pop("Empty stack for drop".to_string())?;
+    forth.pop()?;
     Ok(())
 }
 
 pub fn nip(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for first element in nip".to_string())?;
-    forth.pop("Empty stack for second element in nip".to_string())?;
+    let a = forth.pop()?;
+    forth.pop()?;
     forth.push(a);
     Ok(())
 }
 
 pub fn tuck(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for first element in swap".to_string())?;
-    let b = forth.pop("Empty stack for second element in swap".to_string())?;
+    let a = forth.pop()?;
+    let b = forth.pop()?;
     forth.push(a);
     forth.push(b);
     forth.push(a);
@@ -95,8 +106,8 @@ pub fn tuck(forth: &mut Forth) -> ForthResult<()> {
 }
 
 pub fn over(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for first element in swap".to_string())?;
-    let b = forth.pop("Empty stack for second element in swap".to_string())?;
+    let a = forth.pop()?;
+    let b = forth.pop()?;
     forth.push(b);
     forth.push(a);
     forth.push(b);
@@ -104,25 +115,40 @@ pub fn over(forth: &mut Forth) -> ForthResult<()> {
 }
 
 pub fn max(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for first element in max".to_string())?;
-    let b = forth.pop("Empty stack for second element in max".to_string())?;
+    let a = forth.pop()?;
+    let b = forth.pop()?;
     forth.push(cmp::max(a, b));
     Ok(())
 }
 
 pub fn min(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for first element in min".to_string())?;
-    let b = forth.pop("Empty stack for second element in min".to_string())?;
+    let a = forth.pop()?;
+    let b = forth.pop()?;
     forth.push(cmp::min(a, b));
     Ok(())
 }
 
-pub fn cr(_: &mut Forth) -> ForthResult<()> {
-    println!();
+pub fn cr(forth: &mut Forth) -> ForthResult<()> {
+    forth.write_output("\n")
+}
+
+pub fn depth(forth: &mut Forth) -> ForthResult<()> {
+    forth.push(forth.depth() as i32);
     Ok(())
 }
 
 pub fn clearstack(forth: &mut Forth) -> ForthResult<()> {
     forth.clear();
     Ok(())
+}
+
+pub fn throw(forth: &mut Forth) -> ForthResult<()> {
+    match forth.pop()? {
+        0 => Ok(()),
+        code => Err(ErrorKind::from_code(code).into()),
+    }
+}
+
+pub fn abort(_: &mut Forth) -> ForthResult<()> {
+    Err(ErrorKind::Abort.into())
 }
\ No newline at end of file
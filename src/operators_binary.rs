@@ -2,15 +2,9 @@ use crate::forth::{Forth, ForthResult};
 
 type BinOperator = fn(i32, i32) -> bool;
 
-fn binary_operator(name: &str, bin_op: BinOperator, forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop(format!(
-        "Empty stack: for first argument for {}",
-        name.to_string()
-    ))?;
-    let b = forth.pop(format!(
-        "Empty stack: for second argument for {}",
-        name.to_string()
-    ))?;
+fn binary_operator(bin_op: BinOperator, forth: &mut Forth) -> ForthResult<()> {
+    let a = forth.pop()?;
+    let b = forth.pop()?;
     if bin_op(a, b) {
         forth.push(-1);
     } else {
@@ -20,31 +14,31 @@ fn binary_operator(name: &str, bin_op: BinOperator, forth: &mut Forth) -> ForthR
 }
 
 pub fn equals(forth: &mut Forth) -> ForthResult<()> {
-    binary_operator("=", |a, b| a == b, forth)
+    binary_operator(|a, b| a == b, forth)
 }
 
 pub fn not_equals(forth: &mut Forth) -> ForthResult<()> {
-    binary_operator("=", |a, b| a != b, forth)
+    binary_operator(|a, b| a != b, forth)
 }
 
 pub fn greater_than(forth: &mut Forth) -> ForthResult<()> {
-    binary_operator(">", |a, b| b > a, forth)
+    binary_operator(|a, b| b > a, forth)
 }
 
 pub fn less_than(forth: &mut Forth) -> ForthResult<()> {
-    binary_operator("<", |a, b| b < a, forth)
+    binary_operator(|a, b| b < a, forth)
 }
 
 pub fn greater_than_equals(forth: &mut Forth) -> ForthResult<()> {
-    binary_operator(">=", |a, b| b >= a, forth)
+    binary_operator(|a, b| b >= a, forth)
 }
 
 pub fn less_than_equals(forth: &mut Forth) -> ForthResult<()> {
-    binary_operator("<=", |a, b| b <= a, forth)
+    binary_operator(|a, b| b <= a, forth)
 }
 
 pub fn invert(forth: &mut Forth) -> ForthResult<()> {
-    let a = forth.pop("Empty stack for invert".to_string())?;
+    let a = forth.pop()?;
     forth.push(if a == 0 { -1 } else { 0 });
     Ok(())
 }
@@ -0,0 +1,28 @@
+use crate::forth::{self, Forth, ForthResult};
+
+pub fn source(forth: &mut Forth) -> ForthResult<()> {
+    let (addr, len) = forth.source();
+    forth.push(addr as i32);
+    forth.push(len as i32);
+    Ok(())
+}
+
+pub fn to_in(forth: &mut Forth) -> ForthResult<()> {
+    forth.push(forth::TO_IN as i32);
+    Ok(())
+}
+
+pub fn parse(forth: &mut Forth) -> ForthResult<()> {
+    let delimiter = forth.pop()?;
+    let (addr, len) = forth.parse(delimiter as u8);
+    forth.push(addr as i32);
+    forth.push(len as i32);
+    Ok(())
+}
+
+pub fn parse_name(forth: &mut Forth) -> ForthResult<()> {
+    let (addr, len) = forth.parse_name();
+    forth.push(addr as i32);
+    forth.push(len as i32);
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use crate::error::ErrorKind;
+use crate::forth::{self, Forth, ForthResult, WordKind, CELL_SIZE};
+
+pub fn fetch(forth: &mut Forth) -> ForthResult<()> {
+    let addr = forth.pop()?;
+    let a = forth.fetch(addr)?;
+    forth.push(a);
+    Ok(())
+}
+
+pub fn store(forth: &mut Forth) -> ForthResult<()> {
+    let addr = forth.pop()?;
+    let a = forth.pop()?;
+    forth.store(addr, a)
+}
+
+pub fn plus_store(forth: &mut Forth) -> ForthResult<()> {
+    let addr = forth.pop()?;
+    let a = forth.pop()?;
+    let b = forth.fetch(addr)?;
+    forth.store(addr, b.wrapping_add(a))
+}
+
+pub fn c_fetch(forth: &mut Forth) -> ForthResult<()> {
+    let addr = forth.pop()?;
+    let c = forth.fetch_char(addr)?;
+    forth.push(c as i32);
+    Ok(())
+}
+
+pub fn c_store(forth: &mut Forth) -> ForthResult<()> {
+    let addr = forth.pop()?;
+    let c = forth.pop()?;
+    forth.store_char(addr, c as u8)
+}
+
+/// The data field of a word defined by CREATE, from its execution token.
+pub fn to_body(forth: &mut Forth) -> ForthResult<()> {
+    let xt = forth.pop()?;
+    match usize::try_from(xt).ok().and_then(|xt| forth.word_kind(xt)) {
+        Some(&WordKind::Created { body, .. }) => {
+            forth.push(body as i32);
+            Ok(())
+        }
+        _ => Err(ErrorKind::NotCreated.into()),
+    }
+}
+
+pub fn here(forth: &mut Forth) -> ForthResult<()> {
+    let here = forth.here();
+    forth.push(here as i32);
+    Ok(())
+}
+
+pub fn state(forth: &mut Forth) -> ForthResult<()> {
+    forth.push(forth::STATE as i32);
+    Ok(())
+}
+
+pub fn base(forth: &mut Forth) -> ForthResult<()> {
+    forth.push(forth::BASE as i32);
+    Ok(())
+}
+
+pub fn hex(forth: &mut Forth) -> ForthResult<()> {
+    forth.set_base(16);
+    Ok(())
+}
+
+pub fn decimal(forth: &mut Forth) -> ForthResult<()> {
+    forth.set_base(10);
+    Ok(())
+}
+
+pub fn octal(forth: &mut Forth) -> ForthResult<()> {
+    forth.set_base(8);
+    Ok(())
+}
+
+pub fn binary(forth: &mut Forth) -> ForthResult<()> {
+    forth.set_base(2);
+    Ok(())
+}
+
+pub fn allot(forth: &mut Forth) -> ForthResult<()> {
+    let n = forth.pop()?;
+    forth.allot(n)
+}
+
+pub fn comma(forth: &mut Forth) -> ForthResult<()> {
+    let a = forth.pop()?;
+    let addr = forth.here() as i32;
+    forth.allot(CELL_SIZE as i32)?;
+    forth.store(addr, a)
+}
+
+pub fn c_comma(forth: &mut Forth) -> ForthResult<()> {
+    let c = forth.pop()?;
+    let addr = forth.here() as i32;
+    forth.allot(1)?;
+    forth.store_char(addr, c as u8)
+}
+
+pub fn cells(forth: &mut Forth) -> ForthResult<()> {
+    let n = forth.pop()?;
+    forth.push(n.wrapping_mul(CELL_SIZE as i32));
+    Ok(())
+}
+
+pub fn cell_plus(forth: &mut Forth) -> ForthResult<()> {
+    let addr = forth.pop()?;
+    forth.push(addr.wrapping_add(CELL_SIZE as i32));
+    Ok(())
+}
+
+pub fn align(forth: &mut Forth) -> ForthResult<()> {
+    forth.align();
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use crate::forth::{Forth, ForthResult};
+
+pub fn dot(forth: &mut Forth) -> ForthResult<()> {
+    let a = forth.pop()?;
+    let number = forth.format_number(a);
+    forth.write_output(&format!("{} ", number))
+}
+
+pub fn u_dot(forth: &mut Forth) -> ForthResult<()> {
+    let a = forth.pop()?;
+    let number = forth.format_unsigned(a as u32);
+    forth.write_output(&format!("{} ", number))
+}
+
+pub fn dot_r(forth: &mut Forth) -> ForthResult<()> {
+    let width = forth.pop()?;
+    let a = forth.pop()?;
+    let number = forth.format_number(a);
+    print_right(forth, &number, width)
+}
+
+pub fn u_dot_r(forth: &mut Forth) -> ForthResult<()> {
+    let width = forth.pop()?;
+    let a = forth.pop()?;
+    let number = forth.format_unsigned(a as u32);
+    print_right(forth, &number, width)
+}
+
+// Shows the stack without touching it, as `<depth> bottom ... top`
+pub fn dot_s(forth: &mut Forth) -> ForthResult<()> {
+    let mut shown = format!("<{}> ", forth.depth());
+    for a in forth.get_stack() {
+        shown.push_str(&format!("{} ", forth.format_number(a)));
+    }
+    forth.write_output(&shown)
+}
+
+pub fn question(forth: &mut Forth) -> ForthResult<()> {
+    let addr = forth.pop()?;
+    let a = forth.fetch(addr)?;
+    let number = forth.format_number(a);
+    forth.write_output(&format!("{} ", number))
+}
+
+pub fn type_string(forth: &mut Forth) -> ForthResult<()> {
+    let len = forth.pop()?;
+    let addr = forth.pop()?;
+    let bytes = forth.bytes_at(addr, len)?.to_vec();
+    forth.write_bytes(&bytes)
+}
+
+pub fn less_number_sign(forth: &mut Forth) -> ForthResult<()> {
+    forth.begin_hold();
+    Ok(())
+}
+
+// Moves the lowest digit of the double number into the picture
+pub fn number_sign(forth: &mut Forth) -> ForthResult<()> {
+    let ud = pop_double(forth)?;
+    let base = forth.radix() as u64;
+    hold_digit(forth, (ud % base) as u32)?;
+    push_double(forth, ud / base);
+    Ok(())
+}
+
+pub fn number_sign_s(forth: &mut Forth) -> ForthResult<()> {
+    let mut ud = pop_double(forth)?;
+    let base = forth.radix() as u64;
+    loop {
+        hold_digit(forth, (ud % base) as u32)?;
+        ud /= base;
+        if ud == 0 {
+            break;
+        }
+    }
+    push_double(forth, ud);
+    Ok(())
+}
+
+pub fn hold(forth: &mut Forth) -> ForthResult<()> {
+    let c = forth.pop()?;
+    forth.hold(c as u8)
+}
+
+pub fn holds(forth: &mut Forth) -> ForthResult<()> {
+    let len = forth.pop()?;
+    let addr = forth.pop()?;
+    let bytes = forth.bytes_at(addr, len)?.to_vec();
+    for &c in bytes.iter().rev() {
+        forth.hold(c)?;
+    }
+    Ok(())
+}
+
+pub fn sign(forth: &mut Forth) -> ForthResult<()> {
+    if forth.pop()? < 0 {
+        forth.hold(b'-')?;
+    }
+    Ok(())
+}
+
+pub fn number_sign_greater(forth: &mut Forth) -> ForthResult<()> {
+    pop_double(forth)?;
+    let (addr, len) = forth.held();
+    forth.push(addr as i32);
+    forth.push(len as i32);
+    Ok(())
+}
+
+fn hold_digit(forth: &mut Forth, digit: u32) -> ForthResult<()> {
+    let c = std::char::from_digit(digit, forth.radix()).unwrap_or('?');
+    forth.hold(c.to_ascii_uppercase() as u8)
+}
+
+// A double cell number is two cells, the high one on top
+fn pop_double(forth: &mut Forth) -> ForthResult<u64> {
+    let high = forth.pop()? as u32 as u64;
+    let low = forth.pop()? as u32 as u64;
+    Ok(high << 32 | low)
+}
+
+fn push_double(forth: &mut Forth, ud: u64) {
+    forth.push(ud as u32 as i32);
+    forth.push((ud >> 32) as u32 as i32);
+}
+
+// Pads on the left up to `width` characters, without the trailing space
+fn print_right(forth: &mut Forth, number: &str, width: i32) -> ForthResult<()> {
+    let width = usize::try_from(width).unwrap_or(0);
+    let padding = " ".repeat(width.saturating_sub(number.len()));
+    forth.write_output(&format!("{}{}", padding, number))
+}
@@ -0,0 +1,72 @@
+use crate::forth::{Forth, ForthResult};
+
+pub fn i(forth: &mut Forth) -> ForthResult<()> {
+    let index = forth.peek_return(0)?;
+    forth.push(index);
+    Ok(())
+}
+
+pub fn j(forth: &mut Forth) -> ForthResult<()> {
+    let index = forth.peek_return(2)?;
+    forth.push(index);
+    Ok(())
+}
+
+pub fn unloop(forth: &mut Forth) -> ForthResult<()> {
+    forth.check_return(2)?;
+    forth.pop_return()?;
+    forth.pop_return()?;
+    Ok(())
+}
+
+pub fn to_r(forth: &mut Forth) -> ForthResult<()> {
+    let a = forth.pop()?;
+    forth.push_return(a);
+    Ok(())
+}
+
+pub fn r_from(forth: &mut Forth) -> ForthResult<()> {
+    let a = forth.pop_return()?;
+    forth.push(a);
+    Ok(())
+}
+
+pub fn r_fetch(forth: &mut Forth) -> ForthResult<()> {
+    let a = forth.pop_return()?;
+    forth.push_return(a);
+    forth.push(a);
+    Ok(())
+}
+
+pub fn rdrop(forth: &mut Forth) -> ForthResult<()> {
+    forth.pop_return()?;
+    Ok(())
+}
+
+pub fn two_to_r(forth: &mut Forth) -> ForthResult<()> {
+    let a = forth.pop()?;
+    let b = forth.pop()?;
+    forth.push_return(b);
+    forth.push_return(a);
+    Ok(())
+}
+
+pub fn two_r_from(forth: &mut Forth) -> ForthResult<()> {
+    forth.check_return(2)?;
+    let a = forth.pop_return()?;
+    let b = forth.pop_return()?;
+    forth.push(b);
+    forth.push(a);
+    Ok(())
+}
+
+pub fn two_r_fetch(forth: &mut Forth) -> ForthResult<()> {
+    two_r_from(forth)?;
+    let a = forth.pop()?;
+    let b = forth.pop()?;
+    forth.push_return(b);
+    forth.push_return(a);
+    forth.push(b);
+    forth.push(a);
+    Ok(())
+}